@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+use tfhe::{generate_keys, get_pbs_count, prelude::*, reset_pbs_count, set_server_key, ConfigBuilder, FheUint128, FheUint32, FheUint64};
+use thesislib::homo::{HomoBool, HomoUint};
+use thesislib::sqrt::isqrt_generic;
+
+/*
+*   Compare the previous encrypted integer square root with the digit by digit one, run with
+*
+*       cargo bench --bench isqrt
+*
+*   Both are run on the encrypted single and double precision radicands with the default parameters,
+*   the bootstraps are counted by tfhe itself
+*/
+
+// The radicands of the single and double precision square root, 4^m <= x < 4^(m + 1)
+const SINGLE_M: u32 = 23;
+const SINGLE_RADICAND: u64 = (1 << 46) + 12_345_678_901;
+const DOUBLE_M: u32 = 52;
+const DOUBLE_RADICAND: u128 = (1 << 104) + 0x1234_5678_9ABC_DEF0_1234_5678;
+
+fn main() {
+    println!("Bootstraps and wall-clock time");
+
+    let (client_key, server_key) = generate_keys(ConfigBuilder::default().build());
+    set_server_key(server_key);
+
+    // Single precision
+    let x: FheUint64 = FheUint64::encrypt(SINGLE_RADICAND, &client_key);
+    let expected: u64 = (SINGLE_RADICAND as f64).sqrt() as u64;
+
+    let (root, previous) = measure(|| isqrt_previous(&x, SINGLE_M));
+    assert_eq!(FheDecrypt::<u64>::decrypt(&root, &client_key), expected);
+
+    let (root, digits) = measure(|| isqrt_generic::<FheUint64, FheUint32>(&x, SINGLE_M).0);
+    assert_eq!(FheDecrypt::<u32>::decrypt(&root, &client_key) as u64, expected);
+
+    report("single precision", previous, digits);
+
+    // Double precision
+    let x: FheUint128 = FheUint128::encrypt(DOUBLE_RADICAND, &client_key);
+    let expected: u128 = DOUBLE_RADICAND.isqrt();
+
+    let (root, previous) = measure(|| isqrt_previous(&x, DOUBLE_M));
+    assert_eq!(FheDecrypt::<u128>::decrypt(&root, &client_key), expected);
+
+    let (root, digits) = measure(|| isqrt_generic::<FheUint128, FheUint64>(&x, DOUBLE_M).0);
+    assert_eq!(FheDecrypt::<u64>::decrypt(&root, &client_key) as u128, expected);
+
+    report("double precision", previous, digits);
+}
+
+// Bootstraps and wall-clock time of the computation
+fn measure<T>(f: impl FnOnce() -> T) -> (T, (u64, Duration)) {
+    reset_pbs_count();
+    let start: Instant = Instant::now();
+    let output: T = f();
+
+    return (output, (get_pbs_count(), start.elapsed()));
+}
+
+fn report(precision: &str, previous: (u64, Duration), digits: (u64, Duration)) {
+    println!("    {precision}: previous {} bootstraps in {:.1?}, digit by digit {} bootstraps in {:.1?}", previous.0, previous.1, digits.0, digits.1);
+}
+
+// The integer square root before the digit by digit one, as it was, it only returned the root
+fn isqrt_previous<T: HomoUint>(x: &T, m: u32) -> T {
+    let mut a: T = T::constant(1 << (2 * m));
+    let mut b: T = a.clone();
+    let mut c: T = a.shr_scalar(2);
+    let mut s: T = a.add(&b).add(&c);
+    let mut can_continue: T::Bool = T::Bool::constant(true);
+
+    for k in 1..= m {
+        let is_eq: T::Bool = x.is_eq(&s);
+        let is_gt: T::Bool = x.is_gt(&s);
+
+        b = T::select(&can_continue, &b.shr_scalar(1), &b);
+        b = T::select(&is_eq.and(&can_continue), &b.add(&c).shr_scalar(m - k), &b);
+        can_continue = is_eq.not().and(&can_continue);
+
+        let is_step: T::Bool = is_gt.and(&can_continue);
+        a = T::select(&is_step, &s, &a);
+        b = T::select(&is_step, &b.add(&c), &b);
+
+        c = T::select(&can_continue, &c.shr_scalar(2), &c);
+        s = T::select(&can_continue, &a.add(&b).add(&c), &s);
+    }
+
+    return b;
+}
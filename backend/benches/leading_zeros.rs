@@ -0,0 +1,60 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rayon::ThreadPool;
+use tfhe::{generate_keys, get_pbs_count, prelude::*, reset_pbs_count, set_server_key, ClientKey, ConfigBuilder, FheUint16, FheUint32, FheUint8};
+use thesislib::bits::{leading_zeros, leading_zeros_parallel, server_key_thread_pool};
+use thesislib::homo::HomoUint;
+
+/*
+*   Compare the binary search for the leading zeros with the parallel comparisons, run with
+*
+*       cargo bench --bench leading_zeros
+*
+*   The parallel one does more work in total, the bootstraps are counted by tfhe itself.
+*   It only finishes earlier, if the pool has enough cores for its comparisons
+*/
+
+const INPUT: u64 = 0b1011;
+
+fn main() {
+    let threads: usize = thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1);
+
+    println!("Bootstraps and wall-clock time on {} threads", threads);
+
+    let (client_key, server_key) = generate_keys(ConfigBuilder::default().build());
+    let pool: ThreadPool = server_key_thread_pool(&server_key, threads).unwrap();
+    set_server_key(server_key);
+
+    compare::<FheUint8, u8>(&FheUint8::encrypt(INPUT, &client_key), &pool, &client_key);
+    compare::<FheUint16, u16>(&FheUint16::encrypt(INPUT, &client_key), &pool, &client_key);
+    compare::<FheUint32, u32>(&FheUint32::encrypt(INPUT, &client_key), &pool, &client_key);
+}
+
+fn compare<T, C>(x: &T, pool: &ThreadPool, client_key: &ClientKey)
+where
+    T: HomoUint + Send + Sync + FheDecrypt<C>,
+    C: Into<u64>,
+{
+    let expected: u64 = (T::BITS - INPUT.ilog2() - 1) as u64;
+
+    let (count, search) = measure(|| leading_zeros(x));
+    assert_eq!(Into::<u64>::into(count.decrypt(client_key)), expected);
+
+    let (count, parallel) = measure(|| leading_zeros_parallel(x, pool));
+    assert_eq!(Into::<u64>::into(count.decrypt(client_key)), expected);
+
+    println!(
+        "    {} bits: binary search {} bootstraps in {:.1?}, parallel {} bootstraps in {:.1?}",
+        T::BITS, search.0, search.1, parallel.0, parallel.1
+    );
+}
+
+// Bootstraps and wall-clock time of the computation
+fn measure<T>(f: impl FnOnce() -> T) -> (T, (u64, Duration)) {
+    reset_pbs_count();
+    let start: Instant = Instant::now();
+    let output: T = f();
+
+    return (output, (get_pbs_count(), start.elapsed()));
+}
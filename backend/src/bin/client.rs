@@ -0,0 +1,355 @@
+use thesislib::{
+    keys::{fheint32_encrypt_public, generate_client_keys, generate_compressed_client_keys, ParameterProfile},
+    metric::DistanceMetric,
+    schema::{CiphertextCoordinate, CiphertextCoordinatesSchema, DistanceFormat, Precision, RegisterServerKeyQuery},
+};
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bincode::{serialize, deserialize};
+use serde::Deserialize;
+use tfhe::{prelude::*, ClientKey, CompactPublicKey, FheBool, FheInt32, FheInt64, FheUint32};
+use uuid::Uuid;
+
+// Files in the client's directory
+const CLIENT_KEY_FILE: &str = "client_key.bin";
+const PUBLIC_KEY_FILE: &str = "public_key.bin";
+const SERVER_KEY_FILE: &str = "server_key.bin";
+const COMPRESSED_SERVER_KEY_FILE: &str = "compressed_server_key.bin";
+const PROFILE_FILE: &str = "profile.txt";
+const KEY_ID_FILE: &str = "key_id.txt";
+const TOKEN_FILE: &str = "token.txt";
+const COORDINATES_FILE: &str = "coordinates.json";
+const RESPONSE_FILE: &str = "response.json";
+
+// Fractional bits of the fixed-point distances, unless given with --frac-bits
+const DEFAULT_FRAC_BITS: u32 = 15;
+
+const USAGE: &str = "Usage:
+    thesis-client keygen <dir> [--profile <profile>] [--function-evaluation] [--compressed]
+    thesis-client encrypt <dir> <xa> <ya> <xb> <yb> [--metric <metric>] [--format <format>] [--frac-bits <bits>]
+    thesis-client register <dir> <url>
+    thesis-client submit <dir> <url>
+    thesis-client decrypt <dir>";
+
+// --------------------------
+// |    Server Responses    |
+// --------------------------
+
+#[derive(Deserialize)]
+struct Response<T> {
+    status: String,
+    data: Option<T>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RegisterData {
+    key_id: Uuid,
+    // Only issued with the first registered key
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DistanceData {
+    distance: Vec<u8>,
+    overflow: Vec<u8>,
+    metric: DistanceMetric,
+    #[serde(default)]
+    format: Option<DistanceFormat>,
+    #[serde(default)]
+    frac_bits: Option<u32>,
+    #[serde(default)]
+    inexact: Option<Vec<u8>>,
+}
+
+impl<T> Response<T> {
+    fn into_data(self) -> Result<T, Box<dyn Error>> {
+        match self.data {
+            Some(data) if self.status == "success" => Ok(data),
+            _ => Err(self.message.unwrap_or(self.status).into()),
+        }
+    }
+}
+
+// ------------------
+// |    Commands    |
+// ------------------
+
+// Serialized server key in the directory and whether it is compressed
+fn read_server_key(dir: &Path) -> Result<(Vec<u8>, bool), Box<dyn Error>> {
+    if dir.join(COMPRESSED_SERVER_KEY_FILE).exists() {
+        return Ok((fs::read(dir.join(COMPRESSED_SERVER_KEY_FILE))?, true));
+    }
+
+    return Ok((fs::read(dir.join(SERVER_KEY_FILE))?, false));
+}
+
+// Profile of the keys in the directory
+fn read_profile(dir: &Path) -> Result<ParameterProfile, Box<dyn Error>> {
+    let name: String = fs::read_to_string(dir.join(PROFILE_FILE))?;
+
+    return ParameterProfile::from_name(name.trim()).ok_or_else(|| format!("Unknown profile {}", name).into());
+}
+
+// Generate the keys locally, the client key never leaves the directory.
+// A compressed server key is a fraction of the size to upload, the server decompresses it, but without the function evaluation
+fn keygen(dir: &Path, profile: ParameterProfile, function_evaluation: bool, compressed: bool) -> Result<(), Box<dyn Error>> {
+    let config = profile
+        .config(function_evaluation)
+        .ok_or_else(|| format!("The profile {} does not support the function evaluation", profile.name()))?;
+
+    if function_evaluation && compressed {
+        return Err("A compressed server key can not carry the function evaluation".into());
+    }
+
+    fs::create_dir_all(dir)?;
+
+    if compressed {
+        let (client_key, public_key, server_key) = generate_compressed_client_keys(config);
+        fs::write(dir.join(CLIENT_KEY_FILE), serialize(&client_key)?)?;
+        fs::write(dir.join(PUBLIC_KEY_FILE), serialize(&public_key)?)?;
+        fs::write(dir.join(COMPRESSED_SERVER_KEY_FILE), serialize(&server_key)?)?;
+    } else {
+        let (client_key, public_key, server_key) = generate_client_keys(config);
+        fs::write(dir.join(CLIENT_KEY_FILE), serialize(&client_key)?)?;
+        fs::write(dir.join(PUBLIC_KEY_FILE), serialize(&public_key)?)?;
+        fs::write(dir.join(SERVER_KEY_FILE), serialize(&server_key)?)?;
+    }
+    fs::write(dir.join(PROFILE_FILE), profile.name())?;
+
+    println!("Keys with the profile {} written to {}", profile.name(), dir.display());
+
+    return Ok(());
+}
+
+// Encrypt two coordinates with the public key into the request, that is submitted later
+fn encrypt(
+    dir: &Path,
+    coordinates: [i32; 4],
+    metric: DistanceMetric,
+    format: DistanceFormat,
+    frac_bits: u32,
+) -> Result<(), Box<dyn Error>> {
+    let public_key: CompactPublicKey = deserialize(&fs::read(dir.join(PUBLIC_KEY_FILE))?)?;
+
+    // Encrypt and serialize the coordinates
+    let [xa, ya, xb, yb] = coordinates.map(|value| serialize(&fheint32_encrypt_public(value, &public_key)));
+
+    // The fractional bits are always sent, so the client knows the scale of the distance
+    let request = CiphertextCoordinatesSchema {
+        server_key: None,
+        key_id: None,
+        compressed: false,
+        profile: Some(read_profile(dir)?),
+        coordinate_a: CiphertextCoordinate { x: xa?, y: ya? },
+        coordinate_b: CiphertextCoordinate { x: xb?, y: yb? },
+        metric,
+        format,
+        precision: if format == DistanceFormat::Fixed { Some(Precision::FracBits(frac_bits)) } else { None },
+        radicand_bits: None,
+    };
+
+    fs::write(dir.join(COORDINATES_FILE), serde_json::to_vec(&request)?)?;
+
+    println!("Encrypted coordinates written to {}", dir.join(COORDINATES_FILE).display());
+
+    return Ok(());
+}
+
+// Register the server key, so the requests only carry its ID. The server issues a token with the first key,
+// which identifies the owner of the keys from then on
+fn register(dir: &Path, url: &str) -> Result<(), Box<dyn Error>> {
+    let (server_key, compressed) = read_server_key(dir)?;
+
+    let query = RegisterServerKeyQuery {
+        compressed,
+        profile: read_profile(dir)?,
+    };
+
+    let mut builder = reqwest::blocking::Client::new()
+        .post(format!("{}/api/keys", url))
+        .query(&query)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .body(server_key);
+
+    if let Some(token) = read_token(dir)? {
+        builder = builder.bearer_auth(token);
+    }
+
+    let response: Response<RegisterData> = builder.send()?.json()?;
+    let data: RegisterData = response.into_data()?;
+
+    fs::write(dir.join(KEY_ID_FILE), data.key_id.to_string())?;
+
+    if let Some(token) = data.token {
+        fs::write(dir.join(TOKEN_FILE), token)?;
+    }
+
+    println!("Server key registered as {}", data.key_id);
+
+    return Ok(());
+}
+
+// Bearer token of the registered keys, if one was issued yet
+fn read_token(dir: &Path) -> Result<Option<String>, Box<dyn Error>> {
+    if !dir.join(TOKEN_FILE).exists() {
+        return Ok(None);
+    }
+
+    return Ok(Some(fs::read_to_string(dir.join(TOKEN_FILE))?.trim().to_string()));
+}
+
+// Submit the encrypted coordinates with the registered key ID, or with the server key itself
+fn submit(dir: &Path, url: &str) -> Result<(), Box<dyn Error>> {
+    let mut request: CiphertextCoordinatesSchema = serde_json::from_slice(&fs::read(dir.join(COORDINATES_FILE))?)?;
+
+    match fs::read_to_string(dir.join(KEY_ID_FILE)) {
+        Ok(key_id) => request.key_id = Some(key_id.trim().parse()?),
+        Err(_) => {
+            let (server_key, compressed) = read_server_key(dir)?;
+            request.server_key = Some(server_key);
+            request.compressed = compressed;
+        }
+    }
+
+    let mut builder = reqwest::blocking::Client::new()
+        .post(format!("{}/api/calc/dist", url))
+        .json(&request);
+
+    if let Some(token) = read_token(dir)? {
+        builder = builder.bearer_auth(token);
+    }
+
+    let response: String = builder.send()?.text()?;
+
+    // Check the response before keeping it
+    let _: DistanceData = serde_json::from_str::<Response<DistanceData>>(&response)?.into_data()?;
+    fs::write(dir.join(RESPONSE_FILE), response)?;
+
+    println!("Encrypted distance written to {}", dir.join(RESPONSE_FILE).display());
+
+    return Ok(());
+}
+
+// Decrypt and print the distance of the last response
+fn decrypt(dir: &Path) -> Result<(), Box<dyn Error>> {
+    let client_key: ClientKey = deserialize(&fs::read(dir.join(CLIENT_KEY_FILE))?)?;
+    let request: CiphertextCoordinatesSchema = serde_json::from_slice(&fs::read(dir.join(COORDINATES_FILE))?)?;
+    let data: DistanceData = serde_json::from_slice::<Response<DistanceData>>(&fs::read(dir.join(RESPONSE_FILE))?)?.into_data()?;
+
+    // The distance is meaningless, if the radicand overflowed
+    let overflow: FheBool = deserialize(&data.overflow)?;
+    if overflow.decrypt(&client_key) {
+        return Err("The distance of the coordinates does not fit into 32 bits".into());
+    }
+
+    // The metrics without a square root are returned as integers
+    if !data.metric.needs_sqrt() {
+        let distance: FheInt32 = deserialize(&data.distance)?;
+        let distance: i32 = distance.decrypt(&client_key);
+
+        println!("{:?} distance: {}", data.metric, distance);
+
+        return Ok(());
+    }
+
+    match data.format.unwrap_or(request.format) {
+        DistanceFormat::Ieee754 => {
+            let distance: FheUint32 = deserialize(&data.distance)?;
+            let distance: f32 = f32::from_bits(distance.decrypt(&client_key));
+
+            // Radicands above 2^24 are rounded before the square root, the server sends the flag along with every IEEE 754 distance
+            let inexact: bool = match data.inexact {
+                Some(inexact) => deserialize::<FheBool>(&inexact)?.decrypt(&client_key),
+                None => false,
+            };
+
+            println!("{:?} distance: {}{}", data.metric, distance, if inexact { " (rounded radicand)" } else { "" });
+        }
+
+        DistanceFormat::Fixed => {
+            let frac_bits: u32 = data.frac_bits
+                .or(request.precision.and_then(|precision| precision.frac_bits()))
+                .unwrap_or(DEFAULT_FRAC_BITS);
+
+            let distance: FheInt64 = deserialize(&data.distance)?;
+            let distance: i64 = distance.decrypt(&client_key);
+
+            println!("{:?} distance: {} (error below {})", data.metric, distance as f64 / (1u64 << frac_bits) as f64, 1.0 / (1u64 << frac_bits) as f64);
+        }
+    }
+
+    return Ok(());
+}
+
+// --------------------------
+// |    Argument Parsing    |
+// --------------------------
+
+// Value of an option, such as --metric manhattan
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    return args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .map(|value| value.as_str());
+}
+
+// Metric and format are parsed with their serde names, the same the server takes
+fn parse_enum<T: serde::de::DeserializeOwned>(value: &str) -> Result<T, Box<dyn Error>> {
+    return Ok(serde_json::from_value(serde_json::Value::String(value.to_string()))?);
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let dir: PathBuf = match args.get(1) {
+        Some(dir) => PathBuf::from(dir),
+        None => return Err(USAGE.into()),
+    };
+
+    match (args[0].as_str(), &args[2..]) {
+        ("keygen", rest) => {
+            let profile: ParameterProfile = option(rest, "--profile").map(parse_enum).transpose()?.unwrap_or_default();
+
+            keygen(
+                &dir,
+                profile,
+                rest.iter().any(|arg| arg == "--function-evaluation"),
+                rest.iter().any(|arg| arg == "--compressed"),
+            )
+        }
+
+        ("encrypt", rest) if rest.len() >= 4 => {
+            let coordinates: [i32; 4] = [rest[0].parse()?, rest[1].parse()?, rest[2].parse()?, rest[3].parse()?];
+
+            let metric: DistanceMetric = option(rest, "--metric").map(parse_enum).transpose()?.unwrap_or_default();
+            let format: DistanceFormat = option(rest, "--format").map(parse_enum).transpose()?.unwrap_or_default();
+            let frac_bits: u32 = option(rest, "--frac-bits").map(str::parse).transpose()?.unwrap_or(DEFAULT_FRAC_BITS);
+
+            encrypt(&dir, coordinates, metric, format, frac_bits)
+        }
+
+        ("register", [url, ..]) => register(&dir, url.trim_end_matches('/')),
+
+        ("submit", [url, ..]) => submit(&dir, url.trim_end_matches('/')),
+
+        ("decrypt", _) => decrypt(&dir),
+
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.is_empty() {
+        eprintln!("{}", USAGE);
+        std::process::exit(1);
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,8 +1,9 @@
-use num_traits::Pow;
 use thesislib::{
-    handler, ieee754::{fheint32_to_ieee754, ieee754_extract_exponent, ieee754_extract_mantissa, ieee754_extract_sign, u32_to_ieee754_2nd}, sqrt::{find_m_recursive, fsqrt, isqrt, isqrt_homo}, structs::AppState
+    handler, structs::{AppState, KeyCache}
 };
 
+use std::sync::Mutex;
+
 use dotenv::dotenv;
 use sqlx::postgres::PgPoolOptions;
 use actix_cors::Cors;
@@ -14,61 +15,8 @@ use actix_web::{
     HttpServer,
 };
 
-use tfhe::{prelude::*, FheBool};
-use tfhe::{ConfigBuilder, generate_keys, set_server_key, FheInt32, FheInt64};
-
-#[actix_web::main] // TODO: uncomment
-async fn main() -> std::io::Result<()> { // TODO: uncomment
-// fn main() {
-    // let config = ConfigBuilder::default().build();
-    // let (client_key, server_key) = generate_keys(config);
-    // set_server_key(server_key);
-
-    // // CONSTANTS
-    // const IEEE754_MANTISSA_SIZE: u64 = 23;
-
-    // // Number
-    // let radicand:i32 = 25;
-    // let radicand_encrypted = FheInt32::encrypt(radicand, &client_key);
-
-    // // IEEE 754
-    // let ieee754: FheInt64 = fheint32_to_ieee754(&radicand_encrypted).cast_into();
-    // let exponent: FheInt64 = (ieee754.clone() >> IEEE754_MANTISSA_SIZE) - 127;
-    // let mantissa: FheInt64 = ieee754.clone() & 0x7FFFFF;
-
-    // let i754: i64 = ieee754.decrypt(&client_key);
-    // println!("i754: [i64] {: ^20} [x] {:#018x} [b] {:064b}", i754, i754, i754);
-
-    // // Calculate stuff
-    // let common_one: FheInt64 = (ieee754.clone() | 0x80000000) >> 31u64;
-    // let hidden_bit: FheInt64 = common_one.clone() << IEEE754_MANTISSA_SIZE;
-    // let normalized_mantissa: FheInt64 = mantissa.clone() | hidden_bit.clone();
-    // let big_n: FheInt64 = (exponent.clone() % 2).eq(&common_one).if_then_else(
-    //     &(normalized_mantissa.clone() << (IEEE754_MANTISSA_SIZE + 1)),
-    //     &(normalized_mantissa.clone() << IEEE754_MANTISSA_SIZE)
-    // );
-    // let new_exponent: FheInt64 = (exponent.clone() % 2).eq(&common_one).if_then_else(
-    //     &(exponent.clone() - 1),
-    //     &exponent.clone()
-    // );
-    // // let first_root: FheInt64 = FheInt64::encrypt(10485760, &client_key);
-    // let first_root: FheInt64 = isqrt_homo(&big_n.clone().cast_into());
-    // let base_exponent: FheInt64 = (127 + (new_exponent.clone() >> 1u64)) << IEEE754_MANTISSA_SIZE;
-    // let root: FheInt64 = base_exponent | first_root;
-
-    // let nmts: i64 = normalized_mantissa.decrypt(&client_key);
-    // let bign: i64 = big_n.decrypt(&client_key);
-    // let newe: i64 = new_exponent.decrypt(&client_key);
-    // let rotd: i64 = root.decrypt(&client_key);
-    // println!("nmts: [i64] {: ^20} [x] {:#018x} [b] {:064b}", nmts, nmts, nmts);
-    // println!("bign: [i64] {: ^20} [x] {:#018x} [b] {:064b}", bign, bign, bign);
-    // println!("newe: [i64] {: ^20} [x] {:#018x} [b] {:064b}", newe, newe, newe);
-    // println!("rotd: [i64] {: ^20} [x] {:#018x} [b] {:064b}", rotd, rotd, rotd);
-    
-    // println!("root: [u32] {: ^20} [x] {:#018x} [b] {:064b}", root_decrypted, root_decrypted, root_decrypted);
-
-
-    // TODO: Uncomment
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
     println!("Starting server");
 
     // Check if RUST_LOG environment variable is unset; set to "actix_web=info" if unset
@@ -105,6 +53,25 @@ async fn main() -> std::io::Result<()> { // TODO: uncomment
     .parse()
     .expect("Invalid port number");
 
+    // Number of the expanded server keys kept in memory, each of them takes hundreds of megabytes
+    let key_cache_size: usize = std::env::var("SERVER_KEY_CACHE_SIZE")
+    .unwrap_or("8".to_string())
+    .parse()
+    .expect("Invalid server key cache size");
+
+    // Number of the registered server keys stored in the database, each of them takes up to a gigabyte
+    let key_store_size: i64 = std::env::var("SERVER_KEY_STORE_SIZE")
+    .unwrap_or("64".to_string())
+    .parse()
+    .expect("Invalid server key store size");
+
+    // Share the state between the workers, the recently used server keys are cached in it
+    let state = web::Data::new(AppState {
+        db: pool,
+        server_keys: Mutex::new(KeyCache::new(key_cache_size)),
+        server_key_store_size: key_store_size,
+    });
+
     // Configure and run Actix Web server
     HttpServer::new(move || {
         let cors = Cors::default()
@@ -122,7 +89,7 @@ async fn main() -> std::io::Result<()> { // TODO: uncomment
             ])
             .supports_credentials(); // TODO: what, how?
         App::new()
-            .app_data(web::Data::new(AppState { db: pool.clone() }))
+            .app_data(state.clone())
             .configure(handler::config)
             .wrap(cors)
             .wrap(Logger::default())
@@ -0,0 +1,177 @@
+use std::f64::consts::PI;
+
+use tfhe::FheInt64;
+
+use crate::fixed::FheFixed;
+use crate::homo::{HomoBool, HomoInt};
+
+/*
+*   FheFixed
+*/
+
+// Sine and cosine of an angle in radians within [-pi, pi]
+pub fn fhefixed_sin_cos<const INT_BITS: u32, const FRAC_BITS: u32>(angle: &FheFixed<INT_BITS, FRAC_BITS>, iterations: u32) -> (FheFixed<INT_BITS, FRAC_BITS>, FheFixed<INT_BITS, FRAC_BITS>) {
+    let (sin, cos) = fheint64_sin_cos(&angle.value, FRAC_BITS, iterations);
+
+    return (FheFixed::new(sin), FheFixed::new(cos));
+}
+
+// Angle of the point (x, y) in radians within [-pi, pi]
+pub fn fhefixed_atan2<const INT_BITS: u32, const FRAC_BITS: u32>(y: &FheFixed<INT_BITS, FRAC_BITS>, x: &FheFixed<INT_BITS, FRAC_BITS>, iterations: u32) -> FheFixed<INT_BITS, FRAC_BITS> {
+    return FheFixed::new(fheint64_atan2(&y.value, &x.value, FRAC_BITS, iterations));
+}
+
+/*
+*   FheInt64
+*/
+
+pub fn fheint64_sin_cos(angle: &FheInt64, frac_bits: u32, iterations: u32) -> (FheInt64, FheInt64) {
+    return sin_cos_generic(angle, frac_bits, iterations);
+}
+
+pub fn fheint64_atan2(y: &FheInt64, x: &FheInt64, frac_bits: u32, iterations: u32) -> FheInt64 {
+    return atan2_generic(y, x, frac_bits, iterations);
+}
+
+/*
+*   i64
+*/
+
+// Sine and cosine in fixed-point with frac_bits fractional bits, mirrors fheint64_sin_cos
+pub fn sin_cos(angle: i64, frac_bits: u32, iterations: u32) -> (i64, i64) {
+    return sin_cos_generic(&angle, frac_bits, iterations);
+}
+
+// Arc tangent of y / x in fixed-point with frac_bits fractional bits, mirrors fheint64_atan2
+pub fn atan2(y: i64, x: i64, frac_bits: u32, iterations: u32) -> i64 {
+    return atan2_generic(&y, &x, frac_bits, iterations);
+}
+
+/*
+*   Generic
+*/
+
+// Rotation mode, the angle is turned towards zero by +-atan(2^-i), while the vector (K, 0) is rotated along with it.
+// CORDIC only converges within [-pi/2, pi/2], the angles beyond are rotated by pi, which flips the signs of both results
+pub fn sin_cos_generic<T: HomoInt>(angle: &T, frac_bits: u32, iterations: u32) -> (T, T) {
+    let pi: i128 = to_fixed(PI, frac_bits);
+    let half_pi: i128 = to_fixed(PI / 2.0, frac_bits);
+
+    let is_above: T::Bool = angle.is_gt_scalar(half_pi);
+    let is_below: T::Bool = angle.is_lt_scalar(-half_pi);
+    let is_flipped: T::Bool = is_above.or(&is_below);
+
+    let mut z: T = T::select(&is_above, &angle.sub_scalar(pi), &T::select(&is_below, &angle.add_scalar(pi), angle));
+    let mut x: T = T::constant(cordic_gain(frac_bits, iterations));
+    let mut y: T = T::constant(0);
+
+    for i in 0..iterations {
+        let atan: i128 = to_fixed(2f64.powi(-(i as i32)).atan(), frac_bits);
+        let is_positive: T::Bool = z.is_ge_scalar(0);
+        let x_shifted: T = x.shr_scalar(i);
+        let y_shifted: T = y.shr_scalar(i);
+
+        x = T::select(&is_positive, &x.sub(&y_shifted), &x.add(&y_shifted));
+        y = T::select(&is_positive, &y.add(&x_shifted), &y.sub(&x_shifted));
+        z = T::select(&is_positive, &z.sub_scalar(atan), &z.add_scalar(atan));
+    }
+
+    return (T::select(&is_flipped, &y.neg(), &y), T::select(&is_flipped, &x.neg(), &x));
+}
+
+// Vectoring mode, the point is turned onto the positive x axis, while the angle of the rotations is summed up.
+// Points left of the y axis are mirrored through the origin first, which is a rotation by pi
+pub fn atan2_generic<T: HomoInt>(y: &T, x: &T, frac_bits: u32, iterations: u32) -> T {
+    let pi: i128 = to_fixed(PI, frac_bits);
+
+    let is_left: T::Bool = x.is_lt_scalar(0);
+    let offset: T = T::select(&y.is_ge_scalar(0), &T::constant(pi), &T::constant(-pi));
+
+    let mut z: T = T::select(&is_left, &offset, &T::constant(0));
+    let mut x: T = T::select(&is_left, &x.neg(), x);
+    let mut y: T = T::select(&is_left, &y.neg(), y);
+
+    for i in 0..iterations {
+        let atan: i128 = to_fixed(2f64.powi(-(i as i32)).atan(), frac_bits);
+        let is_positive: T::Bool = y.is_ge_scalar(0);
+        let x_shifted: T = x.shr_scalar(i);
+        let y_shifted: T = y.shr_scalar(i);
+
+        x = T::select(&is_positive, &x.add(&y_shifted), &x.sub(&y_shifted));
+        y = T::select(&is_positive, &y.sub(&x_shifted), &y.add(&x_shifted));
+        z = T::select(&is_positive, &z.add_scalar(atan), &z.sub_scalar(atan));
+    }
+
+    return z;
+}
+
+// The rotations lengthen the vector by prod sqrt(1 + 2^-2i), starting at the inverse makes the results unit length
+fn cordic_gain(frac_bits: u32, iterations: u32) -> i128 {
+    let gain: f64 = (0..iterations).map(|i| 1.0 / (1.0 + 4f64.powi(-(i as i32))).sqrt()).product();
+
+    return to_fixed(gain, frac_bits);
+}
+
+fn to_fixed(value: f64, frac_bits: u32) -> i128 {
+    return (value * (1u64 << frac_bits) as f64).round() as i128;
+}
+
+#[cfg(test)]
+mod tests {
+    use tfhe::prelude::*;
+    use tfhe::ClientKey;
+
+    use super::*;
+    use crate::keys::test_client_key;
+
+    const FRAC_BITS: u32 = 28;
+
+    const ITERATIONS: u32 = 30;
+
+    // The truncated shifts lose about a unit per iteration
+    const TOLERANCE: f64 = 64.0 / (1u64 << FRAC_BITS) as f64;
+
+    fn to_f64(value: i64) -> f64 {
+        return value as f64 / (1u64 << FRAC_BITS) as f64;
+    }
+
+    #[test]
+    fn sin_cos_matches_f64() {
+        for i in -200..=200 {
+            let angle: f64 = PI * i as f64 / 200.0;
+            let (sin, cos) = sin_cos(to_fixed(angle, FRAC_BITS) as i64, FRAC_BITS, ITERATIONS);
+
+            assert!((to_f64(sin) - angle.sin()).abs() <= TOLERANCE, "sin({}) = {}", angle, to_f64(sin));
+            assert!((to_f64(cos) - angle.cos()).abs() <= TOLERANCE, "cos({}) = {}", angle, to_f64(cos));
+        }
+    }
+
+    #[test]
+    fn atan2_matches_f64() {
+        for i in 0..400 {
+            let angle: f64 = PI * (i as f64 - 199.5) / 200.0;
+            let (y, x) = (angle.sin() * 0.75, angle.cos() * 0.75);
+            let actual: i64 = atan2(to_fixed(y, FRAC_BITS) as i64, to_fixed(x, FRAC_BITS) as i64, FRAC_BITS, ITERATIONS);
+
+            assert!((to_f64(actual) - y.atan2(x)).abs() <= TOLERANCE, "atan2({}, {}) = {}", y, x, to_f64(actual));
+        }
+    }
+
+    // Few iterations keep the encrypted runs short, the plaintext run has to match them exactly anyway
+    #[test]
+    fn encrypted_sin_cos_and_atan2_match_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+        let (frac_bits, iterations): (u32, u32) = (16, 8);
+
+        // An angle beyond pi/2, which is rotated by pi, and a point left of the y axis, which is mirrored
+        let angle: FheFixed<4, 16> = FheFixed::encrypt(2.5, client_key);
+        let (sin, cos) = fhefixed_sin_cos(&angle, iterations);
+        let expected: (i64, i64) = sin_cos(to_fixed(2.5, frac_bits) as i64, frac_bits, iterations);
+        assert_eq!((sin.value.decrypt(client_key), cos.value.decrypt(client_key)), expected);
+
+        let (y, x): (f64, f64) = (0.25, -0.5);
+        let angle: FheFixed<4, 16> = fhefixed_atan2(&FheFixed::encrypt(y, client_key), &FheFixed::encrypt(x, client_key), iterations);
+        let actual: i64 = angle.value.decrypt(client_key);
+        assert_eq!(actual, atan2(to_fixed(y, frac_bits) as i64, to_fixed(x, frac_bits) as i64, frac_bits, iterations));
+    }
+}
@@ -0,0 +1,139 @@
+use tfhe::{prelude::*, FheInt32, FheInt64, FheUint128, FheUint32, FheUint64};
+
+/*
+*   FheInt32
+*/
+
+// Absolute difference of two encrypted integers. The difference of two i32 can take 32 bits, so the signed subtraction
+// wraps for distant values, but it is exact modulo 2^32 and the result is read as unsigned
+pub fn fheint32_abs_diff(a: &FheInt32, b: &FheInt32) -> FheUint32 {
+    let max: FheInt32 = a.max(b);
+    let min: FheInt32 = a.min(b);
+
+    return (max - min).cast_into();
+}
+
+// Square of the absolute difference, (2^32 - 1)^2 fits into 64 bits
+pub fn fheint32_squared_diff(a: &FheInt32, b: &FheInt32) -> FheUint64 {
+    let diff: FheUint64 = fheint32_abs_diff(a, b).cast_into();
+
+    return &diff * &diff;
+}
+
+/*
+*   FheInt64
+*/
+
+// Mirrors fheint32_abs_diff
+pub fn fheint64_abs_diff(a: &FheInt64, b: &FheInt64) -> FheUint64 {
+    let max: FheInt64 = a.max(b);
+    let min: FheInt64 = a.min(b);
+
+    return (max - min).cast_into();
+}
+
+// Square of the absolute difference, (2^64 - 1)^2 fits into 128 bits
+pub fn fheint64_squared_diff(a: &FheInt64, b: &FheInt64) -> FheUint128 {
+    let diff: FheUint128 = fheint64_abs_diff(a, b).cast_into();
+
+    return &diff * &diff;
+}
+
+/*
+*   i32, i64
+*/
+
+// Mirrors fheint32_abs_diff
+pub fn abs_diff(a: i32, b: i32) -> u32 {
+    return a.abs_diff(b);
+}
+
+// Mirrors fheint32_squared_diff
+pub fn squared_diff(a: i32, b: i32) -> u64 {
+    let diff: u64 = a.abs_diff(b) as u64;
+
+    return diff * diff;
+}
+
+// Mirrors fheint64_abs_diff
+pub fn abs_diff64(a: i64, b: i64) -> u64 {
+    return a.abs_diff(b);
+}
+
+// Mirrors fheint64_squared_diff
+pub fn squared_diff64(a: i64, b: i64) -> u128 {
+    let diff: u128 = a.abs_diff(b) as u128;
+
+    return diff * diff;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tfhe::ClientKey;
+
+    use crate::keys::test_client_key;
+
+    // The extremes, equal inputs and mixed signs
+    const PAIRS: [(i32, i32); 6] = [(i32::MIN, i32::MAX), (i32::MAX, i32::MIN), (i32::MIN, i32::MIN), (3, 3), (-5, 7), (7, -5)];
+
+    #[test]
+    fn abs_diff_of_edge_cases() {
+        assert_eq!(abs_diff(i32::MIN, i32::MAX), u32::MAX);
+        assert_eq!(abs_diff(i32::MAX, i32::MIN), u32::MAX);
+        assert_eq!(abs_diff(i32::MIN, 0), 1 << 31);
+        assert_eq!(abs_diff(3, 3), 0);
+        assert_eq!(abs_diff(-5, 7), 12);
+        assert_eq!(abs_diff(7, -5), 12);
+
+        assert_eq!(abs_diff64(i64::MIN, i64::MAX), u64::MAX);
+        assert_eq!(abs_diff64(-5, 7), 12);
+    }
+
+    #[test]
+    fn squared_diff_of_edge_cases() {
+        assert_eq!(squared_diff(i32::MIN, i32::MAX), (u32::MAX as u64) * (u32::MAX as u64));
+        assert_eq!(squared_diff(i32::MIN, i32::MIN), 0);
+        assert_eq!(squared_diff(-5, 7), 144);
+
+        assert_eq!(squared_diff64(i64::MIN, i64::MAX), (u64::MAX as u128) * (u64::MAX as u128));
+        assert_eq!(squared_diff64(7, -5), 144);
+    }
+
+    #[test]
+    fn encrypted_abs_diff_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+
+        for (a, b) in PAIRS {
+            let diff: FheUint32 = fheint32_abs_diff(&FheInt32::encrypt(a, client_key), &FheInt32::encrypt(b, client_key));
+            let diff: u32 = diff.decrypt(client_key);
+
+            assert_eq!(diff, abs_diff(a, b), "|{} - {}|", a, b);
+        }
+    }
+
+    #[test]
+    fn encrypted_squared_diff_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+        let (a, b) = (i32::MIN, i32::MAX);
+
+        let diff: FheUint64 = fheint32_squared_diff(&FheInt32::encrypt(a, client_key), &FheInt32::encrypt(b, client_key));
+        let diff: u64 = diff.decrypt(client_key);
+
+        assert_eq!(diff, squared_diff(a, b));
+    }
+
+    #[test]
+    fn encrypted_fheint64_diff_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+
+        for (a, b) in [(i64::MIN, i64::MAX), (7, -5)] {
+            let (x, y): (FheInt64, FheInt64) = (FheInt64::encrypt(a, client_key), FheInt64::encrypt(b, client_key));
+            let diff: u64 = fheint64_abs_diff(&x, &y).decrypt(client_key);
+            let squared: u128 = fheint64_squared_diff(&x, &y).decrypt(client_key);
+
+            assert_eq!(diff, abs_diff64(a, b), "|{} - {}|", a, b);
+            assert_eq!(squared, squared_diff64(a, b), "|{} - {}|^2", a, b);
+        }
+    }
+}
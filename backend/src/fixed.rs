@@ -0,0 +1,229 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+use tfhe::{prelude::*, ClientKey, FheBool, FheInt32, FheInt64, FheUint64};
+
+use crate::sqrt::isqrt_generic;
+
+/*
+*   FheFixed
+*/
+
+// Encrypted signed fixed-point number, the value times 2^FRAC_BITS is held in a FheInt64.
+// The products take twice the bits, hence INT_BITS + FRAC_BITS is at most 31, overflows past INT_BITS are not detected
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FheFixed<const INT_BITS: u32, const FRAC_BITS: u32> {
+    pub value: FheInt64,
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> FheFixed<INT_BITS, FRAC_BITS> {
+    const FITS: () = assert!(INT_BITS + FRAC_BITS <= 31, "FheFixed holds at most 31 bits");
+
+    pub const FRACTIONAL_BITS: u32 = FRAC_BITS;
+
+    // The mul, div and sqrt truncate, so their results are less than one unit in the last place off
+    pub const ERROR_BOUND: f64 = 1.0 / (1u64 << FRAC_BITS) as f64;
+
+    pub fn new(value: FheInt64) -> Self {
+        let () = Self::FITS;
+
+        return FheFixed { value };
+    }
+
+    pub fn encrypt(value: f64, client_key: &ClientKey) -> Self {
+        return Self::new(FheInt64::encrypt(Self::to_raw(value), client_key));
+    }
+
+    pub fn encrypt_f32(value: f32, client_key: &ClientKey) -> Self {
+        return Self::encrypt(value as f64, client_key);
+    }
+
+    pub fn encrypt_trivial(value: f64) -> Self {
+        return Self::new(FheInt64::encrypt_trivial(Self::to_raw(value)));
+    }
+
+    pub fn decrypt(&self, client_key: &ClientKey) -> f64 {
+        let raw: i64 = self.value.decrypt(client_key);
+
+        return raw as f64 / (1u64 << FRAC_BITS) as f64;
+    }
+
+    pub fn decrypt_f32(&self, client_key: &ClientKey) -> f32 {
+        return self.decrypt(client_key) as f32;
+    }
+
+    pub fn from_fheint32(value: &FheInt32) -> Self {
+        let value: FheInt64 = value.clone().cast_into();
+
+        return Self::new(value << FRAC_BITS as u64);
+    }
+
+    pub fn from_fheint64(value: &FheInt64) -> Self {
+        return Self::new(value.clone() << FRAC_BITS as u64);
+    }
+
+    // Square root of an encrypted integer below 4^INT_BITS, without the conversion to IEEE 754
+    pub fn sqrt_of(radicand: &FheInt64) -> Self {
+        return Self::new(fheint64_sqrt_fixed(radicand, INT_BITS, FRAC_BITS));
+    }
+
+    pub fn sqrt(&self) -> Self {
+        return Self::new(fheint64_sqrt_shifted(&self.value, FRAC_BITS, INT_BITS + FRAC_BITS));
+    }
+
+    // Round to the nearest fixed-point value
+    fn to_raw(value: f64) -> i64 {
+        return (value * (1u64 << FRAC_BITS) as f64).round() as i64;
+    }
+}
+
+// The same as FheFixed::sqrt_of, for when the precision is only known at runtime, the root is scaled by 2^frac_bits
+pub fn fheint64_sqrt_fixed(radicand: &FheInt64, int_bits: u32, frac_bits: u32) -> FheInt64 {
+    assert!(int_bits + frac_bits <= 32, "The root does not fit into {} bits", int_bits + frac_bits);
+
+    return fheint64_sqrt_shifted(radicand, 2 * frac_bits, int_bits + frac_bits);
+}
+
+// Integer square root of radicand * 2^shift, which is below 4^root_bits, negative radicands give zero
+fn fheint64_sqrt_shifted(radicand: &FheInt64, shift: u32, root_bits: u32) -> FheInt64 {
+    let is_negative: FheBool = radicand.lt(0i64);
+    let radicand: FheInt64 = is_negative.if_then_else(&FheInt64::encrypt_trivial(0i64), radicand);
+    let radicand: FheUint64 = radicand.cast_into();
+
+    let (root, _): (FheUint64, FheUint64) = isqrt_generic(&(radicand << shift as u64), root_bits - 1);
+
+    return root.cast_into();
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> FheEq<&FheFixed<INT_BITS, FRAC_BITS>> for FheFixed<INT_BITS, FRAC_BITS> {
+    fn eq(&self, other: &Self) -> FheBool {
+        return self.value.eq(&other.value);
+    }
+
+    fn ne(&self, other: &Self) -> FheBool {
+        return self.value.ne(&other.value);
+    }
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> FheOrd<&FheFixed<INT_BITS, FRAC_BITS>> for FheFixed<INT_BITS, FRAC_BITS> {
+    fn lt(&self, other: &Self) -> FheBool {
+        return self.value.lt(&other.value);
+    }
+
+    fn le(&self, other: &Self) -> FheBool {
+        return self.value.le(&other.value);
+    }
+
+    fn gt(&self, other: &Self) -> FheBool {
+        return self.value.gt(&other.value);
+    }
+
+    fn ge(&self, other: &Self) -> FheBool {
+        return self.value.ge(&other.value);
+    }
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> FheMin<&FheFixed<INT_BITS, FRAC_BITS>> for FheFixed<INT_BITS, FRAC_BITS> {
+    type Output = Self;
+
+    fn min(&self, other: &Self) -> Self {
+        return Self::new(self.value.min(&other.value));
+    }
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> FheMax<&FheFixed<INT_BITS, FRAC_BITS>> for FheFixed<INT_BITS, FRAC_BITS> {
+    type Output = Self;
+
+    fn max(&self, other: &Self) -> Self {
+        return Self::new(self.value.max(&other.value));
+    }
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> Neg for &FheFixed<INT_BITS, FRAC_BITS> {
+    type Output = FheFixed<INT_BITS, FRAC_BITS>;
+
+    fn neg(self) -> Self::Output {
+        return FheFixed::new(-&self.value);
+    }
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> Add<&FheFixed<INT_BITS, FRAC_BITS>> for &FheFixed<INT_BITS, FRAC_BITS> {
+    type Output = FheFixed<INT_BITS, FRAC_BITS>;
+
+    fn add(self, other: &FheFixed<INT_BITS, FRAC_BITS>) -> Self::Output {
+        return FheFixed::new(&self.value + &other.value);
+    }
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> Sub<&FheFixed<INT_BITS, FRAC_BITS>> for &FheFixed<INT_BITS, FRAC_BITS> {
+    type Output = FheFixed<INT_BITS, FRAC_BITS>;
+
+    fn sub(self, other: &FheFixed<INT_BITS, FRAC_BITS>) -> Self::Output {
+        return FheFixed::new(&self.value - &other.value);
+    }
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> Mul<&FheFixed<INT_BITS, FRAC_BITS>> for &FheFixed<INT_BITS, FRAC_BITS> {
+    type Output = FheFixed<INT_BITS, FRAC_BITS>;
+
+    // The product has 2 * FRAC_BITS fractional bits, the arithmetic shift rounds towards negative infinity
+    fn mul(self, other: &FheFixed<INT_BITS, FRAC_BITS>) -> Self::Output {
+        return FheFixed::new((&self.value * &other.value) >> FRAC_BITS as u64);
+    }
+}
+
+impl<const INT_BITS: u32, const FRAC_BITS: u32> Div<&FheFixed<INT_BITS, FRAC_BITS>> for &FheFixed<INT_BITS, FRAC_BITS> {
+    type Output = FheFixed<INT_BITS, FRAC_BITS>;
+
+    // The dividend is scaled up first, so that the quotient keeps FRAC_BITS, it rounds towards zero
+    fn div(self, other: &FheFixed<INT_BITS, FRAC_BITS>) -> Self::Output {
+        return FheFixed::new((self.value.clone() << FRAC_BITS as u64) / &other.value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::test_client_key;
+
+    type Fixed = FheFixed<8, 8>;
+
+    #[test]
+    fn to_raw_rounds_to_the_nearest() {
+        assert_eq!(Fixed::to_raw(2.5), 640);
+        assert_eq!(Fixed::to_raw(-1.25), -320);
+        assert_eq!(Fixed::to_raw(1.0 / 512.0), 1);
+        assert_eq!(Fixed::to_raw(1.0 / 1024.0), 0);
+        assert_eq!(Fixed::to_raw(-3.0 / 1024.0), -1);
+    }
+
+    #[test]
+    fn encrypted_fixed_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+        let a: Fixed = Fixed::encrypt(2.5, client_key);
+        let b: Fixed = Fixed::encrypt(-1.25, client_key);
+
+        assert_eq!(a.decrypt(client_key), 2.5);
+        assert_eq!((&a + &b).decrypt(client_key), 1.25);
+        assert_eq!((&a - &b).decrypt(client_key), 3.75);
+        assert_eq!((-&b).decrypt(client_key), 1.25);
+        assert_eq!((&a * &b).decrypt(client_key), -3.125);
+        assert!(b.lt(&a).decrypt(client_key));
+
+        // Both round to the fixed-point value below the exact one
+        assert_eq!(Fixed::encrypt(2.0, client_key).sqrt().decrypt(client_key), (2f64.sqrt() * 256.0).floor() / 256.0);
+        assert_eq!(Fixed::sqrt_of(&FheInt64::encrypt(10i64, client_key)).decrypt(client_key), (10f64.sqrt() * 256.0).floor() / 256.0);
+    }
+
+    // The quotient is truncated towards zero, also when one or both of the operands are negative
+    #[test]
+    fn encrypted_fixed_div_rounds_towards_zero() {
+        let client_key: &ClientKey = test_client_key();
+
+        for (x, y) in [(1.0, 3.0), (-1.0, 3.0), (-2.5, -0.75)] {
+            let quotient: f64 = (&Fixed::encrypt(x, client_key) / &Fixed::encrypt(y, client_key)).decrypt(client_key);
+
+            assert_eq!(quotient, (x / y * 256.0).trunc() / 256.0, "{} / {}", x, y);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::f64::consts::PI;
+
+use tfhe::{prelude::*, FheInt32, FheInt64, FheUint64};
+
+use crate::cordic::{atan2, fheint64_atan2, fheint64_sin_cos, sin_cos};
+use crate::sqrt::isqrt_generic;
+
+// Mean radius of the Earth in metres
+pub const EARTH_RADIUS: f64 = 6_371_000.0;
+
+// The encrypted coordinates are in microdegrees, which fit into an i32 and resolve about 0.1 m
+pub const MICRODEGREES_PER_DEGREE: f64 = 1_000_000.0;
+
+// Fractional bits of the encrypted angles and trigonometric values. The errors of the CORDIC iterations dominate,
+// which keeps the distances within 3 m of the exact ones, the antipodes included
+const HAVERSINE_FRAC_BITS: u32 = 30;
+
+// The CORDIC gains about one bit per iteration
+const HAVERSINE_ITERATIONS: u32 = HAVERSINE_FRAC_BITS + 2;
+
+// The microdegrees are converted to radians by a multiplication and a shift by this many bits, the product of 180 degrees fits into an i64
+const RADIANS_SHIFT: u32 = 30;
+
+/*
+*   FheInt32
+*/
+
+// Great-circle distance in metres between two points given in microdegrees, by the haversine formula.
+// The haversine a is kept with twice the fractional bits, so that the square roots of short distances are still exact.
+// Near the antipodes 1 - a cancels, so it is summed up from its own non-negative terms instead, the haversine of the distance
+// to the antipode of the second point
+pub fn fheint32_haversine(lat_a: &FheInt32, lon_a: &FheInt32, lat_b: &FheInt32, lon_b: &FheInt32) -> FheInt32 {
+    let f: u64 = HAVERSINE_FRAC_BITS as u64;
+
+    let phi_a: FheInt64 = fheint32_to_radians(lat_a);
+    let phi_b: FheInt64 = fheint32_to_radians(lat_b);
+    let delta_phi: FheInt64 = (phi_b.clone() - &phi_a) >> 1u64;
+    let sigma_phi: FheInt64 = (phi_b.clone() + &phi_a) >> 1u64;
+    let delta_lambda: FheInt64 = (fheint32_to_radians(lon_b) - fheint32_to_radians(lon_a)) >> 1u64;
+
+    let (_, cos_a) = fheint64_sin_cos(&phi_a, HAVERSINE_FRAC_BITS, HAVERSINE_ITERATIONS);
+    let (_, cos_b) = fheint64_sin_cos(&phi_b, HAVERSINE_FRAC_BITS, HAVERSINE_ITERATIONS);
+    let (sin_delta, _) = fheint64_sin_cos(&delta_phi, HAVERSINE_FRAC_BITS, HAVERSINE_ITERATIONS);
+    let (sin_sigma, _) = fheint64_sin_cos(&sigma_phi, HAVERSINE_FRAC_BITS, HAVERSINE_ITERATIONS);
+    let (sin_lambda, cos_lambda) = fheint64_sin_cos(&delta_lambda, HAVERSINE_FRAC_BITS, HAVERSINE_ITERATIONS);
+
+    // a = sin^2(delta_phi / 2) + cos(phi_a) * cos(phi_b) * sin^2(delta_lambda / 2)
+    // 1 - a = sin^2(sigma_phi / 2) + cos(phi_a) * cos(phi_b) * cos^2(delta_lambda / 2), both with 2 * HAVERSINE_FRAC_BITS fractional bits
+    let cosines: FheInt64 = (&cos_a * &cos_b) >> f;
+    let a: FheInt64 = &sin_delta * &sin_delta + &((&cosines * &sin_lambda) >> f) * &sin_lambda;
+    let rest: FheInt64 = &sin_sigma * &sin_sigma + &((&cosines * &cos_lambda) >> f) * &cos_lambda;
+
+    // The roots of the values with twice the fractional bits have HAVERSINE_FRAC_BITS again
+    let root_a: FheInt64 = fheint64_haversine_sqrt(&a);
+    let root_rest: FheInt64 = fheint64_haversine_sqrt(&rest);
+
+    // c = 2 * atan2(sqrt(a), sqrt(1 - a)), the distance is rounded down to whole metres
+    let c: FheInt64 = fheint64_atan2(&root_a, &root_rest, HAVERSINE_FRAC_BITS, HAVERSINE_ITERATIONS) << 1u64;
+    let distance: FheInt64 = (c * EARTH_RADIUS as i64) >> f;
+
+    return distance.cast_into();
+}
+
+// The angle in radians with HAVERSINE_FRAC_BITS fractional bits
+fn fheint32_to_radians(microdegrees: &FheInt32) -> FheInt64 {
+    let microdegrees: FheInt64 = microdegrees.clone().cast_into();
+
+    return (microdegrees * radians_scale()) >> RADIANS_SHIFT as u64;
+}
+
+// The truncations may push the value slightly past [0, 1], the root of it has HAVERSINE_FRAC_BITS fractional bits
+fn fheint64_haversine_sqrt(value: &FheInt64) -> FheInt64 {
+    let one: i64 = 1 << (2 * HAVERSINE_FRAC_BITS);
+    let value: FheInt64 = value.max(&FheInt64::encrypt_trivial(0i64)).min(&FheInt64::encrypt_trivial(one));
+    let radicand: FheUint64 = value.cast_into();
+    let (root, _): (FheUint64, FheUint64) = isqrt_generic(&radicand, HAVERSINE_FRAC_BITS);
+
+    return root.cast_into();
+}
+
+/*
+*   i32
+*/
+
+// Plaintext counterpart of fheint32_haversine in the same fixed-point arithmetic, it gives the same metres bit for bit
+pub fn haversine_fixed(lat_a: i32, lon_a: i32, lat_b: i32, lon_b: i32) -> i32 {
+    let f: u32 = HAVERSINE_FRAC_BITS;
+
+    let phi_a: i64 = to_radians(lat_a);
+    let phi_b: i64 = to_radians(lat_b);
+    let delta_phi: i64 = (phi_b - phi_a) >> 1;
+    let sigma_phi: i64 = (phi_b + phi_a) >> 1;
+    let delta_lambda: i64 = (to_radians(lon_b) - to_radians(lon_a)) >> 1;
+
+    let (_, cos_a) = sin_cos(phi_a, f, HAVERSINE_ITERATIONS);
+    let (_, cos_b) = sin_cos(phi_b, f, HAVERSINE_ITERATIONS);
+    let (sin_delta, _) = sin_cos(delta_phi, f, HAVERSINE_ITERATIONS);
+    let (sin_sigma, _) = sin_cos(sigma_phi, f, HAVERSINE_ITERATIONS);
+    let (sin_lambda, cos_lambda) = sin_cos(delta_lambda, f, HAVERSINE_ITERATIONS);
+
+    let cosines: i64 = (cos_a * cos_b) >> f;
+    let a: i64 = sin_delta * sin_delta + ((cosines * sin_lambda) >> f) * sin_lambda;
+    let rest: i64 = sin_sigma * sin_sigma + ((cosines * cos_lambda) >> f) * cos_lambda;
+
+    let c: i64 = atan2(haversine_sqrt(a), haversine_sqrt(rest), f, HAVERSINE_ITERATIONS) << 1;
+
+    return ((c * EARTH_RADIUS as i64) >> f) as i32;
+}
+
+fn to_radians(microdegrees: i32) -> i64 {
+    return (microdegrees as i64 * radians_scale()) >> RADIANS_SHIFT;
+}
+
+fn haversine_sqrt(value: i64) -> i64 {
+    let radicand: u64 = value.clamp(0, 1 << (2 * HAVERSINE_FRAC_BITS)) as u64;
+    let (root, _): (u64, u64) = isqrt_generic(&radicand, HAVERSINE_FRAC_BITS);
+
+    return root as i64;
+}
+
+// Radians per microdegree with HAVERSINE_FRAC_BITS + RADIANS_SHIFT fractional bits
+fn radians_scale() -> i64 {
+    return (PI / 180.0 / MICRODEGREES_PER_DEGREE * (1u64 << (HAVERSINE_FRAC_BITS + RADIANS_SHIFT)) as f64).round() as i64;
+}
+
+/*
+*   f64
+*/
+
+// Great-circle distance in metres between two points given in degrees, the floating point counterpart of fheint32_haversine
+pub fn haversine(lat_a: f64, lon_a: f64, lat_b: f64, lon_b: f64) -> f64 {
+    let (phi_a, phi_b) = (lat_a.to_radians(), lat_b.to_radians());
+    let delta_phi: f64 = (phi_b - phi_a) / 2.0;
+    let sigma_phi: f64 = (phi_b + phi_a) / 2.0;
+    let delta_lambda: f64 = (lon_b - lon_a).to_radians() / 2.0;
+
+    let cosines: f64 = phi_a.cos() * phi_b.cos();
+    let a: f64 = delta_phi.sin().powi(2) + cosines * delta_lambda.sin().powi(2);
+    let rest: f64 = sigma_phi.sin().powi(2) + cosines * delta_lambda.cos().powi(2);
+
+    return 2.0 * a.sqrt().atan2(rest.sqrt()) * EARTH_RADIUS;
+}
+
+#[cfg(test)]
+mod tests {
+    use tfhe::ClientKey;
+
+    use super::*;
+    use crate::keys::test_client_key;
+
+    fn to_microdegrees(degrees: f64) -> i32 {
+        return (degrees * MICRODEGREES_PER_DEGREE).round() as i32;
+    }
+
+    fn assert_haversine_fixed(lat_a: f64, lon_a: f64, lat_b: f64, lon_b: f64) {
+        let expected: f64 = haversine(lat_a, lon_a, lat_b, lon_b);
+        let actual: i32 = haversine_fixed(to_microdegrees(lat_a), to_microdegrees(lon_a), to_microdegrees(lat_b), to_microdegrees(lon_b));
+
+        assert!((actual as f64 - expected).abs() <= 3.0, "({}, {}) to ({}, {}) = {}, expected {}", lat_a, lon_a, lat_b, lon_b, actual, expected);
+    }
+
+    #[test]
+    fn haversine_of_known_distances() {
+        // A degree along the equator and a quarter of a meridian
+        assert!((haversine(0.0, 0.0, 0.0, 1.0) - EARTH_RADIUS * PI / 180.0).abs() < 1e-6);
+        assert!((haversine(0.0, 10.0, 90.0, 10.0) - EARTH_RADIUS * PI / 2.0).abs() < 1e-6);
+        assert!((haversine(0.0, 0.0, 0.0, 180.0) - EARTH_RADIUS * PI).abs() < 1e-6);
+        assert!((haversine(-89.0, 0.0, 89.0, 180.0) - EARTH_RADIUS * PI).abs() < 1e-6);
+        assert_eq!(haversine(48.8566, 2.3522, 48.8566, 2.3522), 0.0);
+
+        // Paris to London is about 343.5 km on the mean radius
+        let distance: f64 = haversine(48.8566, 2.3522, 51.5074, -0.1278);
+        assert!((distance - 343_500.0).abs() < 1_000.0, "{}", distance);
+        assert_eq!(distance, haversine(51.5074, -0.1278, 48.8566, 2.3522));
+    }
+
+    #[test]
+    fn haversine_fixed_is_within_three_metres() {
+        // The poles, the antipodes and the same point
+        assert_haversine_fixed(90.0, 0.0, -90.0, 0.0);
+        assert_haversine_fixed(-89.0, 0.0, 89.0, 180.0);
+        assert_haversine_fixed(0.0, -180.0, 0.0, 180.0);
+        assert_haversine_fixed(48.8566, 2.3522, 48.8566, 2.3522);
+
+        let random = |range: f64| (rand::random::<f64>() - 0.5) * range;
+
+        for _ in 0..10_000 {
+            let (lat, lon): (f64, f64) = (random(180.0), random(360.0));
+            let (lat_offset, lon_offset): (f64, f64) = (random(0.02), random(0.02));
+
+            // Anywhere, close to the antipode and close to the point
+            assert_haversine_fixed(lat, lon, random(180.0), random(360.0));
+            assert_haversine_fixed(lat, lon, (-lat + lat_offset).clamp(-90.0, 90.0), lon - 180.0f64.copysign(lon) + lon_offset);
+            assert_haversine_fixed(lat, lon, (lat + lat_offset).clamp(-90.0, 90.0), lon + lon_offset);
+        }
+    }
+
+    #[test]
+    fn encrypted_haversine_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+        let (lat_a, lon_a, lat_b, lon_b): (i32, i32, i32, i32) = (48_856_600, 2_352_200, 51_507_400, -127_800);
+
+        let encrypt = |value: i32| FheInt32::encrypt(value, client_key);
+        let distance: FheInt32 = fheint32_haversine(&encrypt(lat_a), &encrypt(lon_a), &encrypt(lat_b), &encrypt(lon_b));
+        let distance: i32 = distance.decrypt(client_key);
+
+        assert_eq!(distance, haversine_fixed(lat_a, lon_a, lat_b, lon_b));
+    }
+}
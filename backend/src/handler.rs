@@ -1,20 +1,60 @@
 use crate::ieee754::fheint32_to_ieee754;
 use crate::{
-    structs::AppState, 
+    structs::{AppState, RegisteredKey},
+    keys::{deserialize_server_key, ParameterProfile},
     model::PlaintextDistances,
     model::CiphertextDistances,
+    model::ServerKeys,
     schema::PlaintextCoordinatesSchema,
     schema::CiphertextCoordinatesSchema,
-    ieee754::u32_to_ieee754_2nd,
-    sqrt::fsqrt,
+    schema::DistanceFormat,
+    schema::RegisterServerKeyQuery,
+    schema::PlaintextLocationsSchema,
+    schema::CiphertextLocationsSchema,
+    geo::{fheint32_haversine, haversine, MICRODEGREES_PER_DEGREE},
+    sqrt::fheuint32_fsqrt,
+    fixed::fheint64_sqrt_fixed,
+    lut::fheuint8_sqrt_lut,
 };
+#[cfg(feature = "dev-insecure-keygen")]
+use crate::{
+    schema::EncryptCoordinatesSchema,
+    schema::InitKeysQuery,
+    keys::generate_client_keys,
+};
+#[cfg(feature = "dev-insecure-keygen")]
+use tfhe::{ClientKey, Config};
 
-use actix_web::{get, post, delete, web, HttpResponse, Responder};
+use actix_web::{get, post, delete, guard, http::header, web, HttpRequest, HttpResponse, Responder};
 use serde_json::json;
 use bincode::{serialize, deserialize};
-use tfhe::{generate_keys, prelude::*, set_server_key, ClientKey, ConfigBuilder, ServerKey};
-use tfhe::FheInt32;
+use tfhe::{prelude::*, set_server_key, ServerKey};
+use tfhe::{FheBool, FheInt32, FheInt64, FheUint32, FheUint8};
 use sha2::{Sha256, Digest};
+use uuid::Uuid;
+
+// Integer bits of the fixed-point distances, the radicands of the FheInt32 coordinates are below 4^16
+const DISTANCE_INT_BITS: u32 = 16;
+
+// Fractional bits of the fixed-point distances, unless the client asks for a precision
+const DISTANCE_FRAC_BITS: u32 = 15;
+
+// Upper bound of a serialized server key in the body of a registration. A default key takes 207 MB, 41 MB compressed
+// and 968 MB with the function evaluation, a high precision one takes about 1.65 GB even compressed and is not accepted
+const SERVER_KEY_LIMIT: usize = 1 << 30;
+
+// Registered server keys of a single owner, the owner has to delete one of them before registering another
+const SERVER_KEYS_PER_OWNER: i64 = 4;
+
+// Upper bound of a server key sent along with a computation, which fits the compressed keys of the default and the small message profile.
+// Every other key has to be registered
+const INLINE_SERVER_KEY_LIMIT: usize = 64 << 20;
+
+// Upper bound of the JSON computation requests, every byte takes up to four characters, the ciphertexts fit next to the compressed keys
+const COMPUTE_JSON_LIMIT: usize = 4 * INLINE_SERVER_KEY_LIMIT;
+
+// Radicands and roots up to this width are computed by a single table lookup
+const LUT_BITS: u32 = 8;
 
 // ----------------------
 // |    Health Check    |
@@ -96,7 +136,7 @@ async fn admin_dump_test_handler(
     data: web::Data<AppState>,
 ) -> impl Responder {
     let query_result = sqlx::query_as!(
-        CiphertextDistances,
+        PlaintextDistances,
         "SELECT * FROM plaintextdistances"
     )
     .fetch_all(&data.db)
@@ -159,17 +199,17 @@ async fn calculate_distance_plaintext(
     let pbx: i32 = deserialize(&body.coordinate_b.x).unwrap();
     let pby: i32 = deserialize(&body.coordinate_b.y).unwrap();
 
-    // Select max and min from coordinates
-    let x1: i32 = std::cmp::max(pax, pbx);
-    let x2: i32 = std::cmp::min(pax, pbx);
-    let y1: i32 = std::cmp::max(pay, pby);
-    let y2: i32 = std::cmp::min(pay, pby);
-
-    // Compute the radicand
-    let radicand: i32 = (x2 - x1) * (x2 - x1) + (y2 - y1) * (y2 - y1);
+    // Combine the differences of the coordinates by the requested metric, the Euclidean one into the radicand, mirrors the encrypted computation
+    let radicand: i32 = match body.metric.combine((pax, pay), (pbx, pby)) {
+        Some(radicand) => radicand,
+        None => {
+            return HttpResponse::UnprocessableEntity()
+                .json(serde_json::json!({"status": "error","message": "The distance of the coordinates does not fit into 32 bits"}));
+        }
+    };
 
-    // Serialize the radicand
-    let rs: Vec<u8> = serialize(&radicand).unwrap();
+    // Serialize the radicand together with the metric
+    let rs: Vec<u8> = serialize(&(body.metric, radicand)).unwrap();
 
     // Search for the radicand
     let query_result = sqlx::query_as!(
@@ -217,11 +257,13 @@ async fn calculate_distance_plaintext(
     // let r754: u32 = u32_to_ieee754_2nd(radicand as u32);
     let r754: u32 = radicand as u32;
 
-    // Compute the square root
-    let sr: f32 = f32::sqrt(r754 as f32);
-
-    // Serialize the square root
-    let srs: Vec<u8> = serialize(&sr).unwrap();
+    // Compute and serialize the square root, only the Euclidean distance needs one and is a float.
+    // The other metrics are serialized as the exact integer, an i32 like the coordinates
+    let srs: Vec<u8> = if body.metric.needs_sqrt() {
+        serialize(&f32::sqrt(r754 as f32)).unwrap()
+    } else {
+        serialize(&radicand).unwrap()
+    };
 
     // Insert the square root into the table
     let query_result = sqlx::query_as!(
@@ -260,120 +302,452 @@ async fn calculate_distance_plaintext(
     }
 }
 
-// ------------------------------
-// |    Production Endpoints    |
-// ------------------------------
+// Compute the great-circle distance in metres between two locations
+#[post("/admin/calc/haversine")]
+async fn calculate_haversine_plaintext(
+    body: web::Json<PlaintextLocationsSchema>,
+) -> impl Responder {
+    // Unwrap the Vector of bytes into 4 byte array and convert into a i32 of microdegrees
+    let lat_a: i32 = deserialize(&body.location_a.lat).unwrap();
+    let lon_a: i32 = deserialize(&body.location_a.lon).unwrap();
+    let lat_b: i32 = deserialize(&body.location_b.lat).unwrap();
+    let lon_b: i32 = deserialize(&body.location_b.lon).unwrap();
 
-// Generate and return a new pair of keys
-#[get("/init")]
-async fn initialize_keys() -> impl Responder {
-    // Generate new pair of keys
-    let config = ConfigBuilder::default().build();
-    let (client_key, server_key) = generate_keys(config);
-    
-    // Serialize the keys
-    let client_key_serialized: Vec<u8> = serialize(&client_key).unwrap();
-    let server_key_serialized: Vec<u8> = serialize(&server_key).unwrap();
+    // Compute the distance from the degrees
+    let distance: f64 = haversine(
+        lat_a as f64 / MICRODEGREES_PER_DEGREE,
+        lon_a as f64 / MICRODEGREES_PER_DEGREE,
+        lat_b as f64 / MICRODEGREES_PER_DEGREE,
+        lon_b as f64 / MICRODEGREES_PER_DEGREE,
+    );
+
+    // Serialize the distance and hash it
+    let ds: Vec<u8> = serialize(&distance).unwrap();
+    let mut hasher = Sha256::new();
+    hasher.update(&ds);
+    let digest = hasher.finalize();
 
-    // JSON the keys and return them
     let response = serde_json::json!({
         "status": "success",
         "data": serde_json::json!({
-            "client_key": client_key_serialized,
-            "server_key": server_key_serialized,
+            "distance": ds,
+            "digest": digest[..],
+            "comment": "calculated"
         })
     });
 
     return HttpResponse::Ok().json(response);
 }
 
-// Generate and return a new pair of keys
-#[post("/encrypt")]
-async fn encrypt(
-    body: web::Json<CiphertextCoordinatesSchema>,
+// -----------------------------
+// |    Server Key Registry    |
+// -----------------------------
+
+// Owner of the request, the hash of its bearer token. The server issues the token with the first registered key,
+// the registered keys are only visible to the requests with the same token
+fn request_owner(req: &HttpRequest) -> Option<String> {
+    let token: &str = req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+
+    return Some(token_hash(token));
+}
+
+// Only the hash of a token is stored
+fn token_hash(token: &str) -> String {
+    return Sha256::digest(token.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+fn issue_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+
+    return bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+fn unauthorized() -> HttpResponse {
+    return HttpResponse::Unauthorized()
+        .json(serde_json::json!({"status": "error","message": "The registered server keys need the bearer token, that was issued with the first one"}));
+}
+
+// Resolve the server key of a request, its parameter profile and whether it has the function evaluation,
+// either a registered one by its ID or the serialized one sent along. A sent key is checked against the profile of the request
+async fn resolve_server_key(
+    data: &AppState,
+    owner: Option<&str>,
+    key_id: Option<Uuid>,
+    server_key: &Option<Vec<u8>>,
+    compressed: bool,
+    profile: Option<ParameterProfile>,
+) -> Result<(ServerKey, ParameterProfile, bool), HttpResponse> {
+    let id: Uuid = match (key_id, server_key) {
+        (Some(id), None) => id,
+        (None, Some(server_key)) => {
+            let profile: ParameterProfile = profile.unwrap_or_default();
+            let (server_key, function_evaluation): (ServerKey, bool) = check_server_key(server_key, compressed, profile)?;
+
+            return Ok((server_key, profile, function_evaluation));
+        }
+        _ => {
+            return Err(HttpResponse::BadRequest()
+                .json(serde_json::json!({"status": "error","message": "Either the key_id or the server_key has to be given"})));
+        }
+    };
+
+    let owner: &str = match owner {
+        Some(owner) => owner,
+        None => return Err(unauthorized()),
+    };
+
+    let not_found = || HttpResponse::NotFound()
+        .json(serde_json::json!({"status": "error","message": format!("The server key {} does not exist", id)}));
+
+    // Take the deserialized key, if it was used recently
+    let cached: Option<RegisteredKey> = data.server_keys.lock().unwrap().get(&id);
+
+    let key: RegisteredKey = match cached {
+        Some(key) => key,
+        None => {
+            let query_result = sqlx::query!(
+                "SELECT owner, profile, server_key, compressed, function_evaluation FROM serverkeys WHERE id = $1",
+                id
+            )
+            .fetch_optional(&data.db)
+            .await;
+
+            let row = match query_result {
+                Ok(Some(row)) => row,
+
+                Ok(None) => {
+                    return Err(not_found());
+                }
+
+                Err(e) => {
+                    return Err(HttpResponse::InternalServerError()
+                        .json(serde_json::json!({"status": "error","message": format!("{:?}", e)})));
+                }
+            };
+
+            // The stored keys were checked at the registration, failing now means the table was tampered with
+            let profile: ParameterProfile = match ParameterProfile::from_name(&row.profile) {
+                Some(profile) => profile,
+                None => {
+                    return Err(HttpResponse::InternalServerError()
+                        .json(serde_json::json!({"status": "error","message": format!("The server key {} has the unknown profile {}", id, row.profile)})));
+                }
+            };
+
+            let server_key: ServerKey = match deserialize_server_key(&row.server_key, row.compressed) {
+                Ok((server_key, _)) => server_key,
+                Err(e) => {
+                    return Err(HttpResponse::InternalServerError()
+                        .json(serde_json::json!({"status": "error","message": format!("The server key {} can not be read: {:?}", id, e)})));
+                }
+            };
+
+            let key = RegisteredKey { owner: row.owner, profile, function_evaluation: row.function_evaluation, server_key };
+            data.server_keys.lock().unwrap().insert(id, key.clone());
+
+            key
+        }
+    };
+
+    if key.owner != owner {
+        return Err(not_found());
+    }
+
+    // The request may not use a different profile than the key was generated with
+    if profile.is_some_and(|profile| profile != key.profile) {
+        return Err(HttpResponse::Conflict()
+            .json(serde_json::json!({"status": "error","message": format!("The server key {} has the profile {}", id, key.profile.name())})));
+    }
+
+    return Ok((key.server_key, key.profile, key.function_evaluation));
+}
+
+// Deserialize a server key, that was sent by a client, and reject it, unless it was generated with the parameters of the profile.
+// Returns the key together with whether it has the function evaluation
+fn check_server_key(bytes: &[u8], compressed: bool, profile: ParameterProfile) -> Result<(ServerKey, bool), HttpResponse> {
+    let (server_key, function_evaluation): (ServerKey, bool) = deserialize_server_key(bytes, compressed)
+        .map_err(|e| HttpResponse::BadRequest()
+            .json(serde_json::json!({"status": "error","message": format!("{:?}", e)})))?;
+
+    if !profile.is_compatible_server_key(&server_key) {
+        return Err(HttpResponse::UnprocessableEntity()
+            .json(serde_json::json!({"status": "error","message": format!("The server key was not generated with the profile {}", profile.name())})));
+    }
+
+    return Ok((server_key, function_evaluation));
+}
+
+// Reject the ciphertexts, that were not encrypted with the parameters of the profile
+fn check_compatibility(profile: ParameterProfile, values: &[&FheInt32]) -> Result<(), HttpResponse> {
+    if values.iter().all(|value| profile.is_compatible_fheint32(value)) {
+        return Ok(());
+    }
+
+    return Err(HttpResponse::UnprocessableEntity()
+        .json(serde_json::json!({"status": "error","message": format!("The ciphertexts were not encrypted with the profile {}", profile.name())})));
+}
+
+// Register a server key and return its ID, which the computations accept in place of the key.
+// The body is the serialized key, the first registration without a bearer token is issued a new one
+async fn register_server_key(
+    req: HttpRequest,
+    query: web::Query<RegisterServerKeyQuery>,
+    body: web::Bytes,
+    data: web::Data<AppState>,
 ) -> impl Responder {
-    // Deserialize the server key
-    let client_key: ClientKey = deserialize(&body.client_key).unwrap();
+    let (owner, token): (String, Option<String>) = match request_owner(&req) {
+        Some(owner) => (owner, None),
+        None => {
+            let token: String = issue_token();
+            (token_hash(&token), Some(token))
+        }
+    };
 
-    // Unwrap the Vector of bytes into 4 byte array and convert into a i32
-    let x1: i32 = deserialize(&body.coordinate_a.x).unwrap();
-    let y1: i32 = deserialize(&body.coordinate_a.y).unwrap();
-    let x2: i32 = deserialize(&body.coordinate_b.x).unwrap();
-    let y2: i32 = deserialize(&body.coordinate_b.y).unwrap();
-    
-    // Encrypt the values
-    let x1_encrypted: FheInt32 = FheInt32::encrypt(x1, &client_key);
-    let y1_encrypted: FheInt32 = FheInt32::encrypt(y1, &client_key);
-    let x2_encrypted: FheInt32 = FheInt32::encrypt(x2, &client_key);
-    let y2_encrypted: FheInt32 = FheInt32::encrypt(y2, &client_key);
+    // Every registration without a token gets a new owner, so the store is bounded in total as well
+    let count_result = sqlx::query!(
+        "SELECT COUNT(*) AS total, COUNT(*) FILTER (WHERE owner = $1) AS owned FROM serverkeys",
+        owner
+    )
+    .fetch_one(&data.db)
+    .await;
 
-    // Serialize the encrypted values
-    let x1_serialized: Vec<u8> = serialize(&x1_encrypted).unwrap();
-    let y1_serialized: Vec<u8> = serialize(&y1_encrypted).unwrap();
-    let x2_serialized: Vec<u8> = serialize(&x2_encrypted).unwrap();
-    let y2_serialized: Vec<u8> = serialize(&y2_encrypted).unwrap();
+    match count_result {
+        Ok(count) => {
+            if count.owned.unwrap_or(0) >= SERVER_KEYS_PER_OWNER {
+                return HttpResponse::TooManyRequests().json(serde_json::json!({
+                    "status": "error",
+                    "message": format!("At most {} server keys can be registered, delete one of them first", SERVER_KEYS_PER_OWNER)
+                }));
+            }
+            if count.total.unwrap_or(0) >= data.server_key_store_size {
+                return HttpResponse::InsufficientStorage()
+                    .json(serde_json::json!({"status": "error","message": "The server key store is full"}));
+            }
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    }
 
-    // JSON the keys and return them
-    let response = serde_json::json!({
-        "status": "success",
-        "data": serde_json::json!({
-            "coordinate_a": {
-                "x": x1_serialized,
-                "y": y1_serialized,
-            },
-            "coordinate_b": {
-                "x": x2_serialized,
-                "y": y2_serialized,
-            },
-        })
-    });
+    // Reject anything, that is not a server key of the profile, a compressed one is stored compressed and only kept expanded in memory
+    let (server_key, function_evaluation): (ServerKey, bool) = match check_server_key(&body, query.compressed, query.profile) {
+        Ok(checked) => checked,
+        Err(response) => return response,
+    };
 
-    return HttpResponse::Ok().json(response);
+    let id: Uuid = Uuid::new_v4();
+
+    let query_result = sqlx::query!(
+        "INSERT INTO serverkeys (id, owner, profile, server_key, compressed, function_evaluation) VALUES ($1, $2, $3, $4, $5, $6)",
+        id,
+        owner,
+        query.profile.name(),
+        &body[..],
+        query.compressed,
+        function_evaluation
+    )
+    .execute(&data.db)
+    .await;
+
+    match query_result {
+        Ok(_) => {
+            data.server_keys.lock().unwrap().insert(id, RegisteredKey { owner, profile: query.profile, function_evaluation, server_key });
+
+            // The token is only ever returned here
+            let response = serde_json::json!({"status": "success", "data": serde_json::json!({
+                "key_id": id,
+                "profile": query.profile,
+                "function_evaluation": function_evaluation,
+                "token": token
+            })});
+
+            return HttpResponse::Ok().json(response);
+        }
+
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    }
 }
 
-// TODO: continue this
-#[post("/calc/dist")]
+// List the server keys of the owner
+#[get("/keys")]
+async fn list_server_keys(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let owner: String = match request_owner(&req) {
+        Some(owner) => owner,
+        None => return unauthorized(),
+    };
+
+    let query_result = sqlx::query_as!(
+        ServerKeys,
+        "SELECT id, owner, profile, compressed, function_evaluation, created_at FROM serverkeys WHERE owner = $1 ORDER BY created_at",
+        owner
+    )
+    .fetch_all(&data.db)
+    .await;
+
+    match query_result {
+        Ok(rows) => {
+            let response = serde_json::json!({"status": "success", "data": serde_json::json!({
+                "keys": rows
+            })});
+
+            return HttpResponse::Ok().json(response);
+        }
+
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    }
+}
+
+// Delete a server key of the owner
+#[delete("/keys/{id}")]
+async fn delete_server_key(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id: Uuid = path.into_inner();
+    let owner: String = match request_owner(&req) {
+        Some(owner) => owner,
+        None => return unauthorized(),
+    };
+
+    let query_result = sqlx::query!(
+        "DELETE FROM serverkeys WHERE id = $1 AND owner = $2",
+        id,
+        owner
+    )
+    .execute(&data.db)
+    .await;
+
+    match query_result {
+        Ok(result) if result.rows_affected() > 0 => {
+            data.server_keys.lock().unwrap().remove(&id);
+
+            let response = serde_json::json!({
+                "status": "success",
+                "message": format!("The server key {} was deleted", id)
+            });
+
+            return HttpResponse::Ok().json(response);
+        }
+
+        Ok(_) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({"status": "error","message": format!("The server key {} does not exist", id)}));
+        }
+
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    }
+}
+
+// ------------------------------
+// |    Production Endpoints    |
+// ------------------------------
+
+// Compute the encrypted distance between two encrypted coordinates
 async fn calculate_distance_ciphertext(
+    req: HttpRequest,
     body: web::Json<CiphertextCoordinatesSchema>,
     data: web::Data<AppState>,
 ) -> impl Responder {
-    // Deserialize the server key and set it
-    let server_key: ServerKey = deserialize(&body.server_key).unwrap();
+    // Check the requested precision, the fixed-point root holds at most 32 bits
+    let frac_bits: u32 = match (body.format, body.precision) {
+        (DistanceFormat::Fixed, None) => DISTANCE_FRAC_BITS,
+        (DistanceFormat::Fixed, Some(precision)) => match precision.frac_bits() {
+            Some(bits) if bits <= 32 - DISTANCE_INT_BITS => bits,
+            _ => {
+                return HttpResponse::BadRequest()
+                    .json(serde_json::json!({"status": "error","message": format!("The precision {:?} can not be reached", precision)}));
+            }
+        },
+        (DistanceFormat::Ieee754, None) => 0,
+        (DistanceFormat::Ieee754, Some(_)) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({"status": "error","message": "The precision can only be chosen for the fixed format"}));
+        }
+    };
+
+    // Resolve the server key and set it
+    let (server_key, profile, function_evaluation): (ServerKey, ParameterProfile, bool) =
+        match resolve_server_key(&data, request_owner(&req).as_deref(), body.key_id, &body.server_key, body.compressed, body.profile).await {
+            Ok(resolved) => resolved,
+            Err(response) => return response,
+        };
     set_server_key(server_key);
 
+    // Small radicands with a root, that fits into a byte, are looked up in a table, the root of a byte takes 4 bits
+    let is_small: bool = body.metric.needs_sqrt()
+        && body.format == DistanceFormat::Fixed
+        && body.radicand_bits.is_some_and(|bits| bits <= LUT_BITS)
+        && frac_bits <= LUT_BITS / 2;
+
+    // The table lookups need the key with the function evaluation
+    if is_small && !function_evaluation {
+        return HttpResponse::UnprocessableEntity()
+            .json(serde_json::json!({"status": "error","message": "The radicand_bits need a server key with the function evaluation"}));
+    }
+
     // Deserialize the coordinations
     let pax: FheInt32 = deserialize(&body.coordinate_a.x).unwrap();
     let pay: FheInt32 = deserialize(&body.coordinate_a.y).unwrap();
     let pbx: FheInt32 = deserialize(&body.coordinate_b.x).unwrap();
     let pby: FheInt32 = deserialize(&body.coordinate_b.y).unwrap();
 
-    // Select max and min from coordinates
-    let x1: FheInt32 = pax.min(&pbx);
-    let x2: FheInt32 = pay.min(&pbx);
-    let y1: FheInt32 = pay.min(&pby);
-    let y2: FheInt32 = pay.min(&pby);
+    // Check the coordinates against the profile of the key
+    if let Err(response) = check_compatibility(profile, &[&pax, &pay, &pbx, &pby]) {
+        return response;
+    }
+
+    // Combine the differences of the coordinates by the requested metric, the Euclidean one into the radicand.
+    // The server can not reject an overflowing radicand, so the client gets an encrypted flag next to the distance
+    let (r, overflow): (FheInt32, FheBool) = body.metric.fheint32_combine((&pax, &pay), (&pbx, &pby));
 
-    // Compute the radicand
-    let r: FheInt32 = (x2.clone() - x1.clone()) * (x2.clone() - x1.clone()) + (y2.clone() - y1.clone()) * (y2.clone() - y1.clone());
-    
-    // Serialize the radicand);
-    let rs: Vec<u8> = serialize(&r).unwrap();
+    // The table only has the radicands of the vouched bits, a larger one is flagged like an overflow instead of being truncated
+    let overflow: FheBool = match body.radicand_bits {
+        Some(bits) if is_small => overflow | r.gt(((1u32 << bits) - 1) as i32),
+        _ => overflow,
+    };
+    let ovs: Vec<u8> = serialize(&overflow).unwrap();
+
+    // Serialize the radicand together with the requested metric, format, precision and the way of the square root
+    let rs: Vec<u8> = serialize(&(body.metric, body.format, frac_bits, is_small, &r)).unwrap();
 
     // Search for the radicand
     let query_result = sqlx::query_as!(
         CiphertextDistances,
-        "SELECT id, distance FROM plaintextdistances WHERE id = $1",
+        "SELECT id, distance, metadata FROM ciphertextdistances WHERE id = $1",
         rs
     )
     .fetch_one(&data.db)
     .await;
 
     match query_result {
-        // Return the square root if it was already computed
+        // Return the square root if it was already computed, together with the metadata it was stored with.
+        // The overflow flag belongs to the coordinates of this request
         Ok(value) => {
-            let response = serde_json::json!({"status": "success", "data": serde_json::json!({
-                "distance": value.distance
-            })});
+            let mut result: serde_json::Value = value.metadata
+                .and_then(|metadata| serde_json::from_str(&metadata).ok())
+                .unwrap_or_else(|| serde_json::json!({"metric": body.metric, "format": body.format}));
+            result["distance"] = serde_json::json!(value.distance);
+            result["overflow"] = serde_json::json!(ovs);
+
+            let response = serde_json::json!({"status": "success", "data": result});
 
             return HttpResponse::Ok().json(response);
         }
@@ -387,16 +761,232 @@ async fn calculate_distance_ciphertext(
         }
     }
 
-    // Convert the radicand into a IEEE 754 format
-    let r754: FheInt32 = fheint32_to_ieee754(&r);
+    // Compute the square root in the requested format
+    let (srs, result): (Vec<u8>, serde_json::Value) = match (body.metric.needs_sqrt(), body.format) {
+        // The other metrics are exact integers already
+        (false, _) => {
+            let srs: Vec<u8> = serialize(&r).unwrap();
 
-    // Compute the square root
-    // let roo: FheInt32 = fsqrt(r754);
+            let result = serde_json::json!({
+                "distance": srs,
+                "overflow": ovs,
+                "metric": body.metric
+            });
+
+            (srs, result)
+        }
+
+        (true, DistanceFormat::Ieee754) => {
+            // Convert the radicand into a IEEE 754 format, radicands above 2^24 get rounded
+            let (r754, inexact) = fheint32_to_ieee754(&r);
+            let r754: FheUint32 = r754.cast_into();
+
+            // Compute the square root
+            let root: FheUint32 = fheuint32_fsqrt(&r754);
+
+            // Serialize the square root and the rounding flag
+            let srs: Vec<u8> = serialize(&root).unwrap();
+            let ins: Vec<u8> = serialize(&inexact).unwrap();
+
+            let result = serde_json::json!({
+                "distance": srs,
+                "overflow": ovs,
+                "metric": body.metric,
+                "format": body.format,
+                "inexact": ins
+            });
+
+            (srs, result)
+        }
+
+        (true, DistanceFormat::Fixed) => {
+            // A small radicand is looked up, otherwise scale the radicand by 4^frac_bits and compute the square root directly on the integer, it is off by less than the error bound
+            let root: FheInt64 = if is_small {
+                let r: FheUint8 = r.cast_into();
+                let root: FheUint8 = fheuint8_sqrt_lut(&r, frac_bits);
+                root.cast_into()
+            } else {
+                let r: FheInt64 = r.cast_into();
+                fheint64_sqrt_fixed(&r, DISTANCE_INT_BITS, frac_bits)
+            };
+
+            // Serialize the square root, the client divides the decrypted value by the scale
+            let srs: Vec<u8> = serialize(&root).unwrap();
+
+            let result = serde_json::json!({
+                "distance": srs,
+                "overflow": ovs,
+                "metric": body.metric,
+                "format": body.format,
+                "frac_bits": frac_bits,
+                "scale": 1u64 << frac_bits,
+                "error_bound": 1.0 / (1u64 << frac_bits) as f64
+            });
+
+            (srs, result)
+        }
+    };
+
+    // The metadata of the result is stored along, everything but the distance and the flag of this request
+    let mut metadata: serde_json::Value = result.clone();
+    if let Some(fields) = metadata.as_object_mut() {
+        fields.remove("distance");
+        fields.remove("overflow");
+    }
+    let ms: String = metadata.to_string();
+
+    // Insert the square root into the table
+    let query_result = sqlx::query_as!(
+        CiphertextDistances,
+        "INSERT INTO ciphertextdistances (id, distance, metadata) VALUES ($1, $2, $3)",
+        rs,
+        srs,
+        ms
+    )
+    .execute(&data.db)
+    .await;
+
+    match query_result {
+        // Return the newly computed square root
+        Ok(_) => {
+            let response = serde_json::json!({
+                "status": "success",
+                "data": result
+            });
+
+            return HttpResponse::Ok().json(response);
+        }
+
+        Err(e) => {
+            println!("{:?}", e.to_string());
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    }
+}
+
+// Compute the encrypted great-circle distance in metres between two encrypted locations
+async fn calculate_haversine_ciphertext(
+    req: HttpRequest,
+    body: web::Json<CiphertextLocationsSchema>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // Resolve the server key and set it
+    let (server_key, profile, _): (ServerKey, ParameterProfile, bool) =
+        match resolve_server_key(&data, request_owner(&req).as_deref(), body.key_id, &body.server_key, body.compressed, body.profile).await {
+            Ok(resolved) => resolved,
+            Err(response) => return response,
+        };
+    set_server_key(server_key);
+
+    // Deserialize the locations
+    let lat_a: FheInt32 = deserialize(&body.location_a.lat).unwrap();
+    let lon_a: FheInt32 = deserialize(&body.location_a.lon).unwrap();
+    let lat_b: FheInt32 = deserialize(&body.location_b.lat).unwrap();
+    let lon_b: FheInt32 = deserialize(&body.location_b.lon).unwrap();
+
+    // Check the locations against the profile of the key
+    if let Err(response) = check_compatibility(profile, &[&lat_a, &lon_a, &lat_b, &lon_b]) {
+        return response;
+    }
+
+    // Compute the distance, rounded down to whole metres
+    let distance: FheInt32 = fheint32_haversine(&lat_a, &lon_a, &lat_b, &lon_b);
+
+    // Serialize the distance
+    let ds: Vec<u8> = serialize(&distance).unwrap();
 
     let response = serde_json::json!({
         "status": "success",
         "data": serde_json::json!({
-            "distance": 1
+            "distance": ds
+        })
+    });
+
+    return HttpResponse::Ok().json(response);
+}
+
+// --------------------------
+// |    Dev-only Endpoints    |
+// --------------------------
+
+// The server sees the client key and the plaintexts in these endpoints, they are only compiled with the
+// dev-insecure-keygen feature. The clients generate their keys with thesislib::keys and encrypt with the public key
+
+// Generate and return new keys, the client key included
+#[cfg(feature = "dev-insecure-keygen")]
+#[get("/init")]
+async fn initialize_keys(
+    query: web::Query<InitKeysQuery>,
+) -> impl Responder {
+    // Generate new keys with the requested profile, optionally with the function evaluation for the table lookups
+    let config: Config = match query.profile.config(query.function_evaluation) {
+        Some(config) => config,
+        None => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({"status": "error","message": format!("The profile {} does not support the function evaluation", query.profile.name())}));
+        }
+    };
+    let (client_key, public_key, server_key) = generate_client_keys(config);
+    
+    // Serialize the keys
+    let client_key_serialized: Vec<u8> = serialize(&client_key).unwrap();
+    let public_key_serialized: Vec<u8> = serialize(&public_key).unwrap();
+    let server_key_serialized: Vec<u8> = serialize(&server_key).unwrap();
+
+    // JSON the keys and return them
+    let response = serde_json::json!({
+        "status": "success",
+        "data": serde_json::json!({
+            "client_key": client_key_serialized,
+            "public_key": public_key_serialized,
+            "server_key": server_key_serialized,
+            "profile": query.profile,
+        })
+    });
+
+    return HttpResponse::Ok().json(response);
+}
+
+// Encrypt the coordinates with the client key
+#[cfg(feature = "dev-insecure-keygen")]
+#[post("/encrypt")]
+async fn encrypt(
+    body: web::Json<EncryptCoordinatesSchema>,
+) -> impl Responder {
+    // Deserialize the server key
+    let client_key: ClientKey = deserialize(&body.client_key).unwrap();
+
+    // Unwrap the Vector of bytes into 4 byte array and convert into a i32
+    let x1: i32 = deserialize(&body.coordinate_a.x).unwrap();
+    let y1: i32 = deserialize(&body.coordinate_a.y).unwrap();
+    let x2: i32 = deserialize(&body.coordinate_b.x).unwrap();
+    let y2: i32 = deserialize(&body.coordinate_b.y).unwrap();
+    
+    // Encrypt the values
+    let x1_encrypted: FheInt32 = FheInt32::encrypt(x1, &client_key);
+    let y1_encrypted: FheInt32 = FheInt32::encrypt(y1, &client_key);
+    let x2_encrypted: FheInt32 = FheInt32::encrypt(x2, &client_key);
+    let y2_encrypted: FheInt32 = FheInt32::encrypt(y2, &client_key);
+
+    // Serialize the encrypted values
+    let x1_serialized: Vec<u8> = serialize(&x1_encrypted).unwrap();
+    let y1_serialized: Vec<u8> = serialize(&y1_encrypted).unwrap();
+    let x2_serialized: Vec<u8> = serialize(&x2_encrypted).unwrap();
+    let y2_serialized: Vec<u8> = serialize(&y2_encrypted).unwrap();
+
+    // JSON the keys and return them
+    let response = serde_json::json!({
+        "status": "success",
+        "data": serde_json::json!({
+            "coordinate_a": {
+                "x": x1_serialized,
+                "y": y1_serialized,
+            },
+            "coordinate_b": {
+                "x": x2_serialized,
+                "y": y2_serialized,
+            },
         })
     });
 
@@ -416,9 +1006,33 @@ pub fn config(conf: &mut web::ServiceConfig) {
         .service(admin_dump_test_handler)
         .service(admin_wipe_test_handler)
         .service(calculate_distance_plaintext)
+        .service(calculate_haversine_plaintext)
+        .service(list_server_keys)
+        .service(delete_server_key)
+        // The routes with the server keys in the body take more than the default limit of the payload
+        .service(
+            web::resource("/keys")
+                .guard(guard::Post())
+                .app_data(web::PayloadConfig::new(SERVER_KEY_LIMIT))
+                .to(register_server_key)
+        )
+        .service(
+            web::resource("/calc/dist")
+                .guard(guard::Post())
+                .app_data(web::JsonConfig::default().limit(COMPUTE_JSON_LIMIT))
+                .to(calculate_distance_ciphertext)
+        )
+        .service(
+            web::resource("/calc/haversine")
+                .guard(guard::Post())
+                .app_data(web::JsonConfig::default().limit(COMPUTE_JSON_LIMIT))
+                .to(calculate_haversine_ciphertext)
+        );
+
+    #[cfg(feature = "dev-insecure-keygen")]
+    let scope = scope
         .service(initialize_keys)
-        .service(encrypt)
-        .service(calculate_distance_ciphertext);
+        .service(encrypt);
 
     conf.service(scope);
 }
\ No newline at end of file
@@ -0,0 +1,563 @@
+use tfhe::{prelude::*, FheBool, FheInt128, FheInt16, FheInt32, FheInt64, FheInt8, FheUint128, FheUint16, FheUint32, FheUint64, FheUint8};
+
+/*
+*   Common operations of the plaintext and the encrypted values, so that the algorithms are written only once
+*   and the plaintext run serves as an exact oracle of the encrypted one
+*/
+
+pub trait HomoBool: Clone {
+    fn constant(value: bool) -> Self;
+    fn and(&self, other: &Self) -> Self;
+    fn or(&self, other: &Self) -> Self;
+    fn xor(&self, other: &Self) -> Self;
+    fn not(&self) -> Self;
+}
+
+// Arithmetic wraps around, shifts take the amount modulo the width and the division by zero gives all ones
+// with the dividend as the remainder, the same as tfhe does
+pub trait HomoUint: Clone {
+    type Bool: HomoBool;
+
+    const BITS: u32;
+
+    fn constant(value: u128) -> Self;
+    fn from_bool(value: &Self::Bool) -> Self;
+    fn select(condition: &Self::Bool, if_true: &Self, if_false: &Self) -> Self;
+
+    fn add(&self, other: &Self) -> Self;
+    fn sub(&self, other: &Self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+    fn and(&self, other: &Self) -> Self;
+    fn or(&self, other: &Self) -> Self;
+    fn not(&self) -> Self;
+    fn shl(&self, amount: &Self) -> Self;
+    fn shr(&self, amount: &Self) -> Self;
+    fn minimum(&self, other: &Self) -> Self;
+    fn div_rem(&self, other: &Self) -> (Self, Self);
+
+    fn add_scalar(&self, value: u128) -> Self;
+    fn sub_scalar(&self, value: u128) -> Self;
+    fn and_scalar(&self, value: u128) -> Self;
+    fn or_scalar(&self, value: u128) -> Self;
+    fn shl_scalar(&self, amount: u32) -> Self;
+    fn shr_scalar(&self, amount: u32) -> Self;
+
+    fn is_eq(&self, other: &Self) -> Self::Bool;
+    fn is_ne(&self, other: &Self) -> Self::Bool;
+    fn is_lt(&self, other: &Self) -> Self::Bool;
+    fn is_gt(&self, other: &Self) -> Self::Bool;
+    fn is_ge(&self, other: &Self) -> Self::Bool;
+
+    fn is_eq_scalar(&self, value: u128) -> Self::Bool;
+    fn is_ne_scalar(&self, value: u128) -> Self::Bool;
+    fn is_lt_scalar(&self, value: u128) -> Self::Bool;
+    fn is_gt_scalar(&self, value: u128) -> Self::Bool;
+    fn is_ge_scalar(&self, value: u128) -> Self::Bool;
+}
+
+// Conversion between the widths, truncating the upper bits
+pub trait HomoCast<T>: HomoUint {
+    fn cast(&self) -> T;
+}
+
+// Signed counterpart of HomoUint, the shifts to the right are arithmetic
+pub trait HomoInt: Clone {
+    type Bool: HomoBool;
+
+    const BITS: u32;
+
+    fn constant(value: i128) -> Self;
+    fn select(condition: &Self::Bool, if_true: &Self, if_false: &Self) -> Self;
+
+    fn add(&self, other: &Self) -> Self;
+    fn sub(&self, other: &Self) -> Self;
+    fn neg(&self) -> Self;
+
+    fn add_scalar(&self, value: i128) -> Self;
+    fn sub_scalar(&self, value: i128) -> Self;
+    fn shr_scalar(&self, amount: u32) -> Self;
+
+    fn is_lt_scalar(&self, value: i128) -> Self::Bool;
+    fn is_gt_scalar(&self, value: i128) -> Self::Bool;
+    fn is_ge_scalar(&self, value: i128) -> Self::Bool;
+}
+
+/*
+*   Plaintext
+*/
+
+impl HomoBool for bool {
+    fn constant(value: bool) -> Self {
+        return value;
+    }
+
+    fn and(&self, other: &Self) -> Self {
+        return *self & *other;
+    }
+
+    fn or(&self, other: &Self) -> Self {
+        return *self | *other;
+    }
+
+    fn xor(&self, other: &Self) -> Self {
+        return *self ^ *other;
+    }
+
+    fn not(&self) -> Self {
+        return !*self;
+    }
+}
+
+macro_rules! impl_homo_uint_plaintext {
+    ($($clear:ty),*) => {
+        $(
+            impl HomoUint for $clear {
+                type Bool = bool;
+
+                const BITS: u32 = <$clear>::BITS;
+
+                fn constant(value: u128) -> Self {
+                    return value as $clear;
+                }
+
+                fn from_bool(value: &bool) -> Self {
+                    return *value as $clear;
+                }
+
+                fn select(condition: &bool, if_true: &Self, if_false: &Self) -> Self {
+                    return if *condition { *if_true } else { *if_false };
+                }
+
+                fn add(&self, other: &Self) -> Self {
+                    return self.wrapping_add(*other);
+                }
+
+                fn sub(&self, other: &Self) -> Self {
+                    return self.wrapping_sub(*other);
+                }
+
+                fn mul(&self, other: &Self) -> Self {
+                    return self.wrapping_mul(*other);
+                }
+
+                fn and(&self, other: &Self) -> Self {
+                    return *self & *other;
+                }
+
+                fn or(&self, other: &Self) -> Self {
+                    return *self | *other;
+                }
+
+                fn not(&self) -> Self {
+                    return !*self;
+                }
+
+                fn shl(&self, amount: &Self) -> Self {
+                    return self.wrapping_shl(*amount as u32);
+                }
+
+                fn shr(&self, amount: &Self) -> Self {
+                    return self.wrapping_shr(*amount as u32);
+                }
+
+                fn minimum(&self, other: &Self) -> Self {
+                    return std::cmp::min(*self, *other);
+                }
+
+                fn div_rem(&self, other: &Self) -> (Self, Self) {
+                    if *other == 0 {
+                        return (<$clear>::MAX, *self);
+                    }
+
+                    return (*self / *other, *self % *other);
+                }
+
+                fn add_scalar(&self, value: u128) -> Self {
+                    return self.wrapping_add(value as $clear);
+                }
+
+                fn sub_scalar(&self, value: u128) -> Self {
+                    return self.wrapping_sub(value as $clear);
+                }
+
+                fn and_scalar(&self, value: u128) -> Self {
+                    return *self & value as $clear;
+                }
+
+                fn or_scalar(&self, value: u128) -> Self {
+                    return *self | value as $clear;
+                }
+
+                fn shl_scalar(&self, amount: u32) -> Self {
+                    return self.wrapping_shl(amount);
+                }
+
+                fn shr_scalar(&self, amount: u32) -> Self {
+                    return self.wrapping_shr(amount);
+                }
+
+                fn is_eq(&self, other: &Self) -> bool {
+                    return *self == *other;
+                }
+
+                fn is_ne(&self, other: &Self) -> bool {
+                    return *self != *other;
+                }
+
+                fn is_lt(&self, other: &Self) -> bool {
+                    return *self < *other;
+                }
+
+                fn is_gt(&self, other: &Self) -> bool {
+                    return *self > *other;
+                }
+
+                fn is_ge(&self, other: &Self) -> bool {
+                    return *self >= *other;
+                }
+
+                fn is_eq_scalar(&self, value: u128) -> bool {
+                    return *self == value as $clear;
+                }
+
+                fn is_ne_scalar(&self, value: u128) -> bool {
+                    return *self != value as $clear;
+                }
+
+                fn is_lt_scalar(&self, value: u128) -> bool {
+                    return *self < value as $clear;
+                }
+
+                fn is_gt_scalar(&self, value: u128) -> bool {
+                    return *self > value as $clear;
+                }
+
+                fn is_ge_scalar(&self, value: u128) -> bool {
+                    return *self >= value as $clear;
+                }
+            }
+        )*
+    };
+}
+
+impl_homo_uint_plaintext!(u8, u16, u32, u64, u128);
+
+macro_rules! impl_homo_cast_plaintext {
+    ($from:ty => $($into:ty),*) => {
+        $(
+            impl HomoCast<$into> for $from {
+                fn cast(&self) -> $into {
+                    return *self as $into;
+                }
+            }
+        )*
+    };
+}
+
+impl_homo_cast_plaintext!(u8 => u8, u16, u32, u64, u128);
+impl_homo_cast_plaintext!(u16 => u8, u16, u32, u64, u128);
+impl_homo_cast_plaintext!(u32 => u8, u16, u32, u64, u128);
+impl_homo_cast_plaintext!(u64 => u8, u16, u32, u64, u128);
+impl_homo_cast_plaintext!(u128 => u8, u16, u32, u64, u128);
+
+macro_rules! impl_homo_int_plaintext {
+    ($($clear:ty),*) => {
+        $(
+            impl HomoInt for $clear {
+                type Bool = bool;
+
+                const BITS: u32 = <$clear>::BITS;
+
+                fn constant(value: i128) -> Self {
+                    return value as $clear;
+                }
+
+                fn select(condition: &bool, if_true: &Self, if_false: &Self) -> Self {
+                    return if *condition { *if_true } else { *if_false };
+                }
+
+                fn add(&self, other: &Self) -> Self {
+                    return self.wrapping_add(*other);
+                }
+
+                fn sub(&self, other: &Self) -> Self {
+                    return self.wrapping_sub(*other);
+                }
+
+                fn neg(&self) -> Self {
+                    return self.wrapping_neg();
+                }
+
+                fn add_scalar(&self, value: i128) -> Self {
+                    return self.wrapping_add(value as $clear);
+                }
+
+                fn sub_scalar(&self, value: i128) -> Self {
+                    return self.wrapping_sub(value as $clear);
+                }
+
+                fn shr_scalar(&self, amount: u32) -> Self {
+                    return self.wrapping_shr(amount);
+                }
+
+                fn is_lt_scalar(&self, value: i128) -> bool {
+                    return *self < value as $clear;
+                }
+
+                fn is_gt_scalar(&self, value: i128) -> bool {
+                    return *self > value as $clear;
+                }
+
+                fn is_ge_scalar(&self, value: i128) -> bool {
+                    return *self >= value as $clear;
+                }
+            }
+        )*
+    };
+}
+
+impl_homo_int_plaintext!(i8, i16, i32, i64, i128);
+
+/*
+*   Encrypted
+*/
+
+impl HomoBool for FheBool {
+    fn constant(value: bool) -> Self {
+        return FheBool::encrypt_trivial(value);
+    }
+
+    fn and(&self, other: &Self) -> Self {
+        return self & other;
+    }
+
+    fn or(&self, other: &Self) -> Self {
+        return self | other;
+    }
+
+    fn xor(&self, other: &Self) -> Self {
+        return self ^ other;
+    }
+
+    fn not(&self) -> Self {
+        return !self.clone();
+    }
+}
+
+macro_rules! impl_homo_uint_encrypted {
+    ($($fhe:ty => $clear:ty),*) => {
+        $(
+            impl HomoUint for $fhe {
+                type Bool = FheBool;
+
+                const BITS: u32 = <$clear>::BITS;
+
+                fn constant(value: u128) -> Self {
+                    return <$fhe>::encrypt_trivial(value as $clear);
+                }
+
+                fn from_bool(value: &FheBool) -> Self {
+                    return value.clone().cast_into();
+                }
+
+                fn select(condition: &FheBool, if_true: &Self, if_false: &Self) -> Self {
+                    return condition.if_then_else(if_true, if_false);
+                }
+
+                fn add(&self, other: &Self) -> Self {
+                    return self + other;
+                }
+
+                fn sub(&self, other: &Self) -> Self {
+                    return self - other;
+                }
+
+                fn mul(&self, other: &Self) -> Self {
+                    return self * other;
+                }
+
+                fn and(&self, other: &Self) -> Self {
+                    return self & other;
+                }
+
+                fn or(&self, other: &Self) -> Self {
+                    return self | other;
+                }
+
+                fn not(&self) -> Self {
+                    return !self.clone();
+                }
+
+                fn shl(&self, amount: &Self) -> Self {
+                    return self << amount;
+                }
+
+                fn shr(&self, amount: &Self) -> Self {
+                    return self >> amount;
+                }
+
+                fn minimum(&self, other: &Self) -> Self {
+                    return FheMin::min(self, other);
+                }
+
+                fn div_rem(&self, other: &Self) -> (Self, Self) {
+                    return DivRem::div_rem(self, other);
+                }
+
+                fn add_scalar(&self, value: u128) -> Self {
+                    return self.clone() + value as $clear;
+                }
+
+                fn sub_scalar(&self, value: u128) -> Self {
+                    return self.clone() - value as $clear;
+                }
+
+                fn and_scalar(&self, value: u128) -> Self {
+                    return self.clone() & value as $clear;
+                }
+
+                fn or_scalar(&self, value: u128) -> Self {
+                    return self.clone() | value as $clear;
+                }
+
+                fn shl_scalar(&self, amount: u32) -> Self {
+                    return self.clone() << amount as $clear;
+                }
+
+                fn shr_scalar(&self, amount: u32) -> Self {
+                    return self.clone() >> amount as $clear;
+                }
+
+                fn is_eq(&self, other: &Self) -> FheBool {
+                    return FheEq::eq(self, other);
+                }
+
+                fn is_ne(&self, other: &Self) -> FheBool {
+                    return FheEq::ne(self, other);
+                }
+
+                fn is_lt(&self, other: &Self) -> FheBool {
+                    return FheOrd::lt(self, other);
+                }
+
+                fn is_gt(&self, other: &Self) -> FheBool {
+                    return FheOrd::gt(self, other);
+                }
+
+                fn is_ge(&self, other: &Self) -> FheBool {
+                    return FheOrd::ge(self, other);
+                }
+
+                fn is_eq_scalar(&self, value: u128) -> FheBool {
+                    return FheEq::eq(self, value as $clear);
+                }
+
+                fn is_ne_scalar(&self, value: u128) -> FheBool {
+                    return FheEq::ne(self, value as $clear);
+                }
+
+                fn is_lt_scalar(&self, value: u128) -> FheBool {
+                    return FheOrd::lt(self, value as $clear);
+                }
+
+                fn is_gt_scalar(&self, value: u128) -> FheBool {
+                    return FheOrd::gt(self, value as $clear);
+                }
+
+                fn is_ge_scalar(&self, value: u128) -> FheBool {
+                    return FheOrd::ge(self, value as $clear);
+                }
+            }
+        )*
+    };
+}
+
+impl_homo_uint_encrypted!(
+    FheUint8 => u8,
+    FheUint16 => u16,
+    FheUint32 => u32,
+    FheUint64 => u64,
+    FheUint128 => u128
+);
+
+macro_rules! impl_homo_cast_encrypted {
+    ($from:ty => $($into:ty),*) => {
+        $(
+            impl HomoCast<$into> for $from {
+                fn cast(&self) -> $into {
+                    return self.clone().cast_into();
+                }
+            }
+        )*
+    };
+}
+
+impl_homo_cast_encrypted!(FheUint8 => FheUint8, FheUint16, FheUint32, FheUint64, FheUint128);
+impl_homo_cast_encrypted!(FheUint16 => FheUint8, FheUint16, FheUint32, FheUint64, FheUint128);
+impl_homo_cast_encrypted!(FheUint32 => FheUint8, FheUint16, FheUint32, FheUint64, FheUint128);
+impl_homo_cast_encrypted!(FheUint64 => FheUint8, FheUint16, FheUint32, FheUint64, FheUint128);
+impl_homo_cast_encrypted!(FheUint128 => FheUint8, FheUint16, FheUint32, FheUint64, FheUint128);
+
+macro_rules! impl_homo_int_encrypted {
+    ($($fhe:ty => $clear:ty, $shift:ty),*) => {
+        $(
+            impl HomoInt for $fhe {
+                type Bool = FheBool;
+
+                const BITS: u32 = <$clear>::BITS;
+
+                fn constant(value: i128) -> Self {
+                    return <$fhe>::encrypt_trivial(value as $clear);
+                }
+
+                fn select(condition: &FheBool, if_true: &Self, if_false: &Self) -> Self {
+                    return condition.if_then_else(if_true, if_false);
+                }
+
+                fn add(&self, other: &Self) -> Self {
+                    return self + other;
+                }
+
+                fn sub(&self, other: &Self) -> Self {
+                    return self - other;
+                }
+
+                fn neg(&self) -> Self {
+                    return -self.clone();
+                }
+
+                fn add_scalar(&self, value: i128) -> Self {
+                    return self.clone() + value as $clear;
+                }
+
+                fn sub_scalar(&self, value: i128) -> Self {
+                    return self.clone() - value as $clear;
+                }
+
+                fn shr_scalar(&self, amount: u32) -> Self {
+                    return self.clone() >> amount as $shift;
+                }
+
+                fn is_lt_scalar(&self, value: i128) -> FheBool {
+                    return FheOrd::lt(self, value as $clear);
+                }
+
+                fn is_gt_scalar(&self, value: i128) -> FheBool {
+                    return FheOrd::gt(self, value as $clear);
+                }
+
+                fn is_ge_scalar(&self, value: i128) -> FheBool {
+                    return FheOrd::ge(self, value as $clear);
+                }
+            }
+        )*
+    };
+}
+
+impl_homo_int_encrypted!(
+    FheInt8 => i8, u8,
+    FheInt16 => i16, u16,
+    FheInt32 => i32, u32,
+    FheInt64 => i64, u64,
+    FheInt128 => i128, u128
+);
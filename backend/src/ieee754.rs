@@ -1,4 +1,16 @@
-use tfhe::{prelude::*, FheBool, FheInt32, FheUint32};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+use tfhe::{prelude::*, ClientKey, FheBool, FheInt32, FheInt64, FheUint32, FheUint64};
+
+use crate::bits::leading_zeros;
+use crate::homo::{HomoBool, HomoCast, HomoUint};
+use crate::sqrt::fheuint32_fsqrt;
+
+// The default quiet NaN
+pub const IEEE754_NAN: u32 = 0x7FC00000;
+
+pub const IEEE754_INFINITY: u32 = 0x7F800000;
 
 /*
 *   IEEE 754 Extraction
@@ -16,6 +28,72 @@ pub fn ieee754_extract_mantissa(input: u32) -> u32 {
     return input & 0x7FFFFF;
 }
 
+pub fn fheuint32_ieee754_extract_sign(input: &FheUint32) -> FheUint32 {
+    return input.clone() >> 31u32;
+}
+
+// The exponent is returned biased, since the unbiased one might be negative
+pub fn fheuint32_ieee754_extract_exponent(input: &FheUint32) -> FheUint32 {
+    return (input.clone() >> 23u32) & 0xFF;
+}
+
+pub fn fheuint32_ieee754_extract_mantissa(input: &FheUint32) -> FheUint32 {
+    return input.clone() & 0x7FFFFF;
+}
+
+// Double precision
+pub fn ieee754_double_extract_sign(input: u64) -> u64 {
+    return input >> 63;
+}
+
+pub fn ieee754_double_extract_exponent(input: u64) -> u64 {
+    return (input >> 52) & 0x7FF;
+}
+
+pub fn ieee754_double_extract_mantissa(input: u64) -> u64 {
+    return input & 0xFFFFFFFFFFFFF;
+}
+
+pub fn fheuint64_ieee754_extract_sign(input: &FheUint64) -> FheUint64 {
+    return input.clone() >> 63u64;
+}
+
+// The exponent is returned biased, since the unbiased one might be negative
+pub fn fheuint64_ieee754_extract_exponent(input: &FheUint64) -> FheUint64 {
+    return (input.clone() >> 52u64) & 0x7FF;
+}
+
+pub fn fheuint64_ieee754_extract_mantissa(input: &FheUint64) -> FheUint64 {
+    return input.clone() & 0xFFFFFFFFFFFFF;
+}
+
+// Mantissa size and bias of the IEEE 754 format with the given width
+pub fn ieee754_format(bits: u32) -> (u32, u128) {
+    match bits {
+        32 => (23, 127),
+        64 => (52, 1023),
+        _ => panic!("There is no IEEE 754 format with {} bits", bits),
+    }
+}
+
+// Shift the leading one of the mantissa to the hidden bit, decrementing the exponent for each shift
+pub fn ieee754_normalize<T: HomoUint>(mantissa: &T, exponent: &mut T) -> T {
+    let (mantissa_size, _) = ieee754_format(T::BITS);
+    let mut m: T = mantissa.clone();
+    let mut k: u32 = 1 << (31 - mantissa_size.leading_zeros());
+
+    while k > 0 {
+        let top: u128 = ((1 << k) - 1) << (mantissa_size + 1 - k);
+        let is_clear: T::Bool = m.and_scalar(top).is_eq_scalar(0);
+
+        m = T::select(&is_clear, &m.shl_scalar(k), &m);
+        *exponent = T::select(&is_clear, &exponent.sub_scalar(k as u128), exponent);
+        k >>= 1;
+    }
+
+    return m;
+}
+
 /*
 *   u32
 */
@@ -54,89 +132,725 @@ pub fn u32_to_ieee754(significand: u32) -> Option<u32> {
     Some(merged)
 }
 
-// The modified algorithm
-pub fn u32_to_ieee754_2nd(input: u32) -> u32 {
-    let mut shifts: u32 = input.clone() & (1 << 23);
-    let zero_found: bool = shifts == 0;
+// The modified algorithm, returns the bits together with a flag, whether the conversion had to round
+pub fn u32_to_ieee754_2nd(input: u32) -> (u32, bool) {
+    return to_ieee754(&input);
+}
 
-    if zero_found {
-        shifts = u32_calculate_shifts(input, shifts);
-    }
+// The second original algorithm
+// https://codegolf.stackexchange.com/questions/252936/convert-an-integer-to-ieee-754-float
+
+/*
+*   FheInt32
+*/
 
-    let exponent = (-(shifts as i32) + 150) as u32;
-    return (exponent << 23) | ((input << shifts) & 0x7FFFFF);
+// Converts the magnitude and puts the sign back, the magnitude of i32::MIN wraps onto itself
+pub fn fheint32_to_ieee754(input: &FheInt32) -> (FheInt32, FheBool) {
+    let is_negative: FheBool = input.lt(0);
+    let magnitude: FheUint32 = is_negative.if_then_else(&-input.clone(), input).cast_into();
+
+    let (ieee754, inexact) = fheuint32_to_ieee754(&magnitude);
+    let sign: FheUint32 = is_negative.cast_into();
+
+    return ((ieee754 | (sign << 31u32)).cast_into(), inexact);
 }
 
-fn u32_calculate_shifts(input: u32, shifts: u32) -> u32 {
-    let mut cloned_shifts: u32 = shifts.clone();
-    let mut zero_found: bool = shifts == 0;
+/*
+*   FheUint32
+*/
 
-    for i in 0..24 {
-        let is_zero: bool = ((input.clone() << i as u32) & (1 << 23)) == 0;
-        zero_found = zero_found & is_zero;
-        let zero_found_number: u32 = zero_found.clone().cast_into();
-        cloned_shifts += zero_found_number;
-    }
+// Mirrors u32_to_ieee754_2nd, the flag is set, whether the conversion had to round
+pub fn fheuint32_to_ieee754(input: &FheUint32) -> (FheUint32, FheBool) {
+    return to_ieee754(input);
+}
 
-    return cloned_shifts;
+/*
+*   u64
+*/
+
+// Double precision counterpart of u32_to_ieee754_2nd
+pub fn u64_to_ieee754(input: u64) -> (u64, bool) {
+    return to_ieee754(&input);
 }
 
-// The second original algorithm
-// https://codegolf.stackexchange.com/questions/252936/convert-an-integer-to-ieee-754-float
+/*
+*   FheUint64
+*/
+
+// Mirrors u64_to_ieee754, the flag is set, whether the conversion had to round
+pub fn fheuint64_to_ieee754(input: &FheUint64) -> (FheUint64, FheBool) {
+    return to_ieee754(input);
+}
 
 /*
-*   FheInt32
+*   Generic
 */
 
-pub fn fheint32_to_ieee754(input: &FheInt32) -> FheInt32 {
-    let mut shifts: FheInt32 = input.clone() & (1 << 23);
-    let zero_found: FheBool = shifts.eq(0);
+// The format is chosen by the width, the flag is set, whether the conversion had to round
+pub fn to_ieee754<T: HomoUint>(input: &T) -> (T, T::Bool) {
+    let (mantissa_size, bias) = ieee754_format(T::BITS);
+    let dropped: u32 = T::BITS - 1 - mantissa_size;
+    let half: u128 = 1 << (dropped - 1);
+
+    // Align the leading one to the highest bit, the lowest bits do not fit into the mantissa
+    let shifts: T = leading_zeros(input);
+    let normalized: T = input.shl(&shifts);
+    let significand: T = normalized.shr_scalar(dropped);
+    let rest: T = normalized.and_scalar((1 << dropped) - 1);
+
+    // Round to the nearest, ties to even
+    let is_odd: T::Bool = significand.and_scalar(1).is_eq_scalar(1);
+    let round: T::Bool = rest.is_gt_scalar(half).or(&rest.is_eq_scalar(half).and(&is_odd));
+    let inexact: T::Bool = rest.is_ne_scalar(0);
+
+    // The hidden bit of the significand is added to the exponent, hence one less than the bias and the width
+    let exponent: T = T::constant(bias + T::BITS as u128 - 2).sub(&shifts);
+    let output: T = exponent.shl_scalar(mantissa_size).add(&significand).add(&T::from_bool(&round));
+
+    // Zero has no leading one
+    let is_zero: T::Bool = input.is_eq_scalar(0);
+
+    return (T::select(&is_zero, input, &output), inexact);
+}
+
+/*
+*   IEEE 754 to integer
+*/
 
-    shifts = zero_found.if_then_else(&fheint32_calculate_shifts(&input, &shifts).cast_into(), &shifts);
+// Rounding of the fractional part, when converting back to an integer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundingMode {
+    Truncate,
+    Floor,
+    Ceil,
+    NearestEven,
+}
 
-    let exponent: FheInt32 = -shifts.clone() + 150;
-    let ushifts: FheUint32 = shifts.cast_into();
-    return (exponent << 23u32) | ((input.clone() << ushifts) & 0x7FFFFF);
+// Out of range values saturate and NaN converts to zero, the same as the `as` cast
+pub fn ieee754_to_i32(input: u32, mode: RoundingMode) -> i32 {
+    return ieee754_to_i64(input, mode).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
 }
 
-fn fheint32_calculate_shifts(input: &FheInt32, shifts: &FheInt32) -> FheInt32 {
-    let mut cloned_shifts: FheInt32 = shifts.clone();
-    let mut zero_found: FheBool = shifts.eq(0);
+pub fn ieee754_to_i64(input: u32, mode: RoundingMode) -> i64 {
+    let (is_negative, magnitude, is_overflow, is_nan) = ieee754_to_magnitude::<u32, u64>(&input, mode, 64);
+
+    if is_nan {
+        return 0;
+    }
 
-    for i in 0..24 {
-        let is_zero: FheBool = (((input.clone() << i as u32) & (1 << 23))).eq(0);
-        zero_found = zero_found & is_zero;
-        let zero_found_number: FheInt32 = zero_found.clone().cast_into();
-        cloned_shifts += zero_found_number;
+    if is_overflow {
+        return if is_negative { i64::MIN } else { i64::MAX };
     }
 
-    return cloned_shifts;
+    return if is_negative { -(magnitude as i64) } else { magnitude as i64 };
+}
+
+// Out of range values saturate and NaN converts to zero, the same as the `as` cast
+pub fn fheuint32_ieee754_to_fheint32(input: &FheUint32, mode: RoundingMode) -> FheInt32 {
+    let (is_negative, magnitude, is_overflow, is_nan) = ieee754_to_magnitude::<FheUint32, FheUint64>(input, mode, 32);
+
+    let magnitude: FheInt32 = magnitude.cast_into();
+    let value: FheInt32 = is_negative.if_then_else(&-magnitude.clone(), &magnitude);
+    let saturated: FheInt32 = is_negative.if_then_else(
+        &FheInt32::encrypt_trivial(i32::MIN),
+        &FheInt32::encrypt_trivial(i32::MAX)
+    );
+    let value: FheInt32 = is_overflow.if_then_else(&saturated, &value);
+
+    return is_nan.if_then_else(&FheInt32::encrypt_trivial(0), &value);
+}
+
+pub fn fheuint32_ieee754_to_fheint64(input: &FheUint32, mode: RoundingMode) -> FheInt64 {
+    let (is_negative, magnitude, is_overflow, is_nan) = ieee754_to_magnitude::<FheUint32, FheUint64>(input, mode, 64);
+
+    let magnitude: FheInt64 = magnitude.cast_into();
+    let value: FheInt64 = is_negative.if_then_else(&-magnitude.clone(), &magnitude);
+    let saturated: FheInt64 = is_negative.if_then_else(
+        &FheInt64::encrypt_trivial(i64::MIN),
+        &FheInt64::encrypt_trivial(i64::MAX)
+    );
+    let value: FheInt64 = is_overflow.if_then_else(&saturated, &value);
+
+    return is_nan.if_then_else(&FheInt64::encrypt_trivial(0), &value);
+}
+
+// Returns the sign, the rounded magnitude and whether the value does not fit into the given bits or is NaN,
+// W has to hold the magnitudes up to the given bits
+fn ieee754_to_magnitude<T, W>(input: &T, mode: RoundingMode, bits: u32) -> (T::Bool, W, T::Bool, T::Bool)
+where
+    T: HomoUint + HomoCast<W>,
+    W: HomoUint<Bool = T::Bool>,
+{
+    let (mantissa_size, bias) = ieee754_format(T::BITS);
+    let exponent_mask: u128 = (1 << (T::BITS - 1 - mantissa_size)) - 1;
+    let is_negative: T::Bool = input.shr_scalar(T::BITS - 1).is_eq_scalar(1);
+    let exponent: T = input.shr_scalar(mantissa_size).and_scalar(exponent_mask);
+    let mantissa: T = input.and_scalar((1 << mantissa_size) - 1);
+
+    let is_nan: T::Bool = exponent.is_eq_scalar(exponent_mask).and(&mantissa.is_ne_scalar(0));
+    let is_overflow: T::Bool = exponent.is_ge_scalar(bias + bits as u128 - 1);
+
+    // Subnormals have no hidden bit and share the exponent with the smallest normal
+    let is_subnormal: T::Bool = exponent.is_eq_scalar(0);
+    let hidden_bit: T = T::from_bool(&is_subnormal.not()).shl_scalar(mantissa_size);
+    let m: W = mantissa.or(&hidden_bit).cast();
+    let e: W = exponent.or(&T::from_bool(&is_subnormal)).cast();
+
+    // Exponents above the mantissa size have no fractional part
+    let integer_exponent: u128 = bias + mantissa_size as u128;
+    let is_integer: T::Bool = e.is_ge_scalar(integer_exponent);
+    let integer: W = m.shl(&e.sub_scalar(integer_exponent));
+
+    // Shift the fractional part out, remembering it for the rounding
+    let shifts: W = W::constant(integer_exponent).sub(&e).minimum(&W::constant(W::BITS as u128 - 1));
+    let truncated: W = m.shr(&shifts);
+    let rest: W = m.sub(&truncated.shl(&shifts));
+    let half: W = W::constant(1).shl(&shifts.sub_scalar(1));
+    let is_odd: T::Bool = truncated.and_scalar(1).is_eq_scalar(1);
+
+    let round: T::Bool = match mode {
+        RoundingMode::Truncate => T::Bool::constant(false),
+        RoundingMode::Floor => rest.is_ne_scalar(0).and(&is_negative),
+        RoundingMode::Ceil => rest.is_ne_scalar(0).and(&is_negative.not()),
+        RoundingMode::NearestEven => rest.is_gt(&half).or(&rest.is_eq(&half).and(&is_odd)),
+    };
+    let fraction: W = truncated.add(&W::from_bool(&round));
+
+    return (is_negative, W::select(&is_integer, &integer, &fraction), is_overflow, is_nan);
 }
 
 /*
-*   FheUint32
+*   Soft float
+*/
+
+// The default quiet NaN and the infinity of the format with the given width
+fn ieee754_special_patterns(bits: u32) -> (u128, u128) {
+    let (mantissa_size, _) = ieee754_format(bits);
+    let infinity: u128 = ((1 << (bits - 1 - mantissa_size)) - 1) << mantissa_size;
+
+    return (infinity | (1 << (mantissa_size - 1)), infinity);
+}
+
+// Offset of the unpacked exponents, the size of the exponent range, so that the normalized subnormals and the products stay positive
+fn ieee754_exponent_offset(bits: u32) -> u128 {
+    let (mantissa_size, _) = ieee754_format(bits);
+
+    return 1 << (bits - 1 - mantissa_size);
+}
+
+// The exponent is offset by ieee754_exponent_offset and the mantissa is normalized to the hidden bit
+struct Ieee754Parts<T: HomoUint> {
+    sign: T::Bool,
+    exponent: T,
+    mantissa: T,
+    is_zero: T::Bool,
+    is_inf: T::Bool,
+    is_nan: T::Bool,
+}
+
+fn ieee754_unpack<T: HomoUint>(input: &T) -> Ieee754Parts<T> {
+    let (mantissa_size, _) = ieee754_format(T::BITS);
+    let offset: u128 = ieee754_exponent_offset(T::BITS);
+    let exponent: T = input.shr_scalar(mantissa_size).and_scalar(offset - 1);
+    let mantissa: T = input.and_scalar((1 << mantissa_size) - 1);
+
+    let is_subnormal: T::Bool = exponent.is_eq_scalar(0);
+    let is_special: T::Bool = exponent.is_eq_scalar(offset - 1);
+    let is_fraction_zero: T::Bool = mantissa.is_eq_scalar(0);
+
+    // Subnormals have no hidden bit and share the exponent with the smallest normal
+    let subnormal_bit: T = T::from_bool(&is_subnormal);
+    let hidden_bit: T = T::from_bool(&is_subnormal.not()).shl_scalar(mantissa_size);
+    let mut e: T = exponent.or(&subnormal_bit).add_scalar(offset);
+    let m: T = ieee754_normalize(&mantissa.or(&hidden_bit), &mut e);
+
+    return Ieee754Parts {
+        sign: input.shr_scalar(T::BITS - 1).is_eq_scalar(1),
+        exponent: e,
+        mantissa: m,
+        is_zero: is_subnormal.and(&is_fraction_zero),
+        is_inf: is_special.and(&is_fraction_zero),
+        is_nan: is_special.and(&is_fraction_zero.not()),
+    };
+}
+
+// Shift the leading one of the significand to the second highest bit, the exponent is decremented for each shift
+fn ieee754_normalize_significand<T, W>(significand: &W, exponent: &T) -> (W, T)
+where
+    W: HomoUint + HomoCast<T>,
+    T: HomoUint,
+{
+    let shifts: W = leading_zeros(significand).sub_scalar(1);
+
+    return (significand.shl(&shifts), exponent.sub(&shifts.cast()));
+}
+
+// Round the significand with the leading one at the second highest bit and the sticky bit in the lowest bit
+fn ieee754_pack<T, W>(sign: &T::Bool, exponent: &T, significand: &W) -> T
+where
+    T: HomoUint + HomoCast<W>,
+    W: HomoUint<Bool = T::Bool> + HomoCast<T>,
+{
+    let (mantissa_size, _) = ieee754_format(T::BITS);
+    let (_, infinity) = ieee754_special_patterns(T::BITS);
+    let offset: u128 = ieee754_exponent_offset(T::BITS);
+    let dropped: u32 = W::BITS - 2 - mantissa_size;
+    let half: u128 = 1 << (dropped - 1);
+
+    // Results below the smallest normal are shifted to the subnormal range
+    let is_subnormal: T::Bool = exponent.is_lt_scalar(offset + 1);
+    let distance: T = T::constant(offset + 1).sub(exponent).minimum(&T::constant(W::BITS as u128 - 1));
+    let shifts: W = T::select(&is_subnormal, &distance, &T::constant(0)).cast();
+    let shifted: W = significand.shr(&shifts);
+    let sticky: W = W::from_bool(&shifted.shl(&shifts).is_ne(significand));
+
+    // Round to the nearest, ties to even
+    let mantissa: W = shifted.shr_scalar(dropped);
+    let rest: W = shifted.and_scalar((1 << dropped) - 1).or(&sticky);
+    let is_odd: T::Bool = mantissa.and_scalar(1).is_eq_scalar(1);
+    let round: T::Bool = rest.is_gt_scalar(half).or(&rest.is_eq_scalar(half).and(&is_odd));
+
+    // The hidden bit is added to the exponent, the rounding may carry into it too
+    let biased: W = T::select(&is_subnormal, &T::constant(0), &exponent.sub_scalar(offset + 1)).cast();
+    let merged: W = biased.shl_scalar(mantissa_size).add(&mantissa).add(&W::from_bool(&round));
+
+    // Too large exponents overflow to the infinity
+    let is_overflow: T::Bool = exponent.is_ge_scalar(2 * offset - 1).or(&merged.is_ge_scalar(infinity));
+    let magnitude: T = T::select(&is_overflow, &T::constant(infinity), &merged.cast());
+
+    return magnitude.or(&T::from_bool(sign).shl_scalar(T::BITS - 1));
+}
+
+// Select the results of the special operands, the NaN takes precedence over the infinity and the zero
+fn ieee754_special<T: HomoUint>(sign: &T::Bool, is_nan: &T::Bool, is_inf: &T::Bool, is_zero: &T::Bool, finite: &T) -> T {
+    let (nan, infinity) = ieee754_special_patterns(T::BITS);
+    let signed_zero: T = T::from_bool(sign).shl_scalar(T::BITS - 1);
+    let signed_inf: T = signed_zero.or_scalar(infinity);
+
+    let mut output: T = T::select(is_zero, &signed_zero, finite);
+    output = T::select(is_inf, &signed_inf, &output);
+
+    return T::select(is_nan, &T::constant(nan), &output);
+}
+
+fn bool_select<B: HomoBool>(condition: &B, if_true: &B, if_false: &B) -> B {
+    return condition.and(if_true).or(&condition.not().and(if_false));
+}
+
+// The format is chosen by the width of T, W has to be twice as wide to hold the product of the mantissas
+pub fn ieee754_add_generic<T, W>(x: &T, y: &T) -> T
+where
+    T: HomoUint + HomoCast<W>,
+    W: HomoUint<Bool = T::Bool> + HomoCast<T>,
+{
+    let (mantissa_size, _) = ieee754_format(T::BITS);
+    let a: Ieee754Parts<T> = ieee754_unpack(x);
+    let b: Ieee754Parts<T> = ieee754_unpack(y);
+
+    // Order the operands by their magnitude
+    let magnitude_mask: u128 = (1 << (T::BITS - 1)) - 1;
+    let is_swapped: T::Bool = y.and_scalar(magnitude_mask).is_gt(&x.and_scalar(magnitude_mask));
+    let sign: T::Bool = bool_select(&is_swapped, &b.sign, &a.sign);
+    let big_exponent: T = T::select(&is_swapped, &b.exponent, &a.exponent);
+    let small_exponent: T = T::select(&is_swapped, &a.exponent, &b.exponent);
+    let big: W = T::select(&is_swapped, &b.mantissa, &a.mantissa).cast();
+    let small: W = T::select(&is_swapped, &a.mantissa, &b.mantissa).cast();
+
+    // Align the smaller operand to the bigger one, keeping the shifted out bits as the sticky bit
+    let big: W = big.shl_scalar(W::BITS - 3 - mantissa_size);
+    let small: W = small.shl_scalar(W::BITS - 3 - mantissa_size);
+    let distance: W = big_exponent.sub(&small_exponent).minimum(&T::constant(W::BITS as u128 - 1)).cast();
+    let aligned: W = small.shr(&distance);
+    let sticky: W = W::from_bool(&aligned.shl(&distance).is_ne(&small));
+    let aligned: W = aligned.or(&sticky);
+
+    // Subtract, when the signs differ, the sum of the two leading ones below the second highest bit is at most at it
+    let is_subtraction: T::Bool = a.sign.xor(&b.sign);
+    let sum: W = W::select(&is_subtraction, &big.sub(&aligned), &big.add(&aligned));
+    let (significand, exponent) = ieee754_normalize_significand(&sum, &big_exponent.add_scalar(1));
+
+    // Exact cancellation gives a positive zero, unless both of the operands are negative
+    let is_cancelled: T::Bool = sum.is_eq_scalar(0);
+    let zero_sign: T::Bool = a.sign.and(&b.sign);
+    let sign: T::Bool = bool_select(&is_cancelled, &zero_sign, &sign);
+    let finite: T = ieee754_pack(&sign, &exponent, &significand);
+
+    let is_nan: T::Bool = a.is_nan.or(&b.is_nan).or(&a.is_inf.and(&b.is_inf).and(&is_subtraction));
+    let is_inf: T::Bool = a.is_inf.or(&b.is_inf);
+    let inf_sign: T::Bool = bool_select(&a.is_inf, &a.sign, &b.sign);
+
+    return ieee754_special(&bool_select(&is_inf, &inf_sign, &sign), &is_nan, &is_inf, &is_cancelled, &finite);
+}
+
+pub fn ieee754_mul_generic<T, W>(x: &T, y: &T) -> T
+where
+    T: HomoUint + HomoCast<W>,
+    W: HomoUint<Bool = T::Bool> + HomoCast<T>,
+{
+    let (mantissa_size, bias) = ieee754_format(T::BITS);
+    let offset: u128 = ieee754_exponent_offset(T::BITS);
+    let a: Ieee754Parts<T> = ieee754_unpack(x);
+    let b: Ieee754Parts<T> = ieee754_unpack(y);
+    let sign: T::Bool = a.sign.xor(&b.sign);
+
+    // The product of the two normalized mantissas has the leading one at twice the mantissa size or one above
+    let left: W = a.mantissa.cast();
+    let right: W = b.mantissa.cast();
+    let product: W = left.mul(&right).shl_scalar(W::BITS - 3 - 2 * mantissa_size);
+    let exponent: T = a.exponent.add(&b.exponent).sub_scalar(offset + bias - 1);
+    let (significand, exponent) = ieee754_normalize_significand(&product, &exponent);
+    let finite: T = ieee754_pack(&sign, &exponent, &significand);
+
+    let is_nan: T::Bool = a.is_nan.or(&b.is_nan)
+        .or(&a.is_inf.and(&b.is_zero))
+        .or(&a.is_zero.and(&b.is_inf));
+    let is_inf: T::Bool = a.is_inf.or(&b.is_inf);
+    let is_zero: T::Bool = a.is_zero.or(&b.is_zero);
+
+    return ieee754_special(&sign, &is_nan, &is_inf, &is_zero, &finite);
+}
+
+pub fn ieee754_div_generic<T, W>(x: &T, y: &T) -> T
+where
+    T: HomoUint + HomoCast<W>,
+    W: HomoUint<Bool = T::Bool> + HomoCast<T>,
+{
+    let (mantissa_size, bias) = ieee754_format(T::BITS);
+    let offset: u128 = ieee754_exponent_offset(T::BITS);
+    let a: Ieee754Parts<T> = ieee754_unpack(x);
+    let b: Ieee754Parts<T> = ieee754_unpack(y);
+    let sign: T::Bool = a.sign.xor(&b.sign);
+
+    // The quotient of the two normalized mantissas has the leading one at the dividend shift or one below
+    let dividend: W = a.mantissa.cast().shl_scalar(W::BITS - 2 - mantissa_size);
+    let divisor: W = b.mantissa.cast();
+    let (quotient, remainder) = dividend.div_rem(&divisor);
+    let sticky: W = W::from_bool(&remainder.is_ne_scalar(0));
+    let quotient: W = quotient.shl_scalar(mantissa_size).or(&sticky);
+    let exponent: T = a.exponent.add_scalar(offset + bias).sub(&b.exponent);
+    let (significand, exponent) = ieee754_normalize_significand(&quotient, &exponent);
+    let finite: T = ieee754_pack(&sign, &exponent, &significand);
+
+    let is_nan: T::Bool = a.is_nan.or(&b.is_nan)
+        .or(&a.is_inf.and(&b.is_inf))
+        .or(&a.is_zero.and(&b.is_zero));
+    let is_inf: T::Bool = a.is_inf.or(&b.is_zero);
+    let is_zero: T::Bool = a.is_zero.or(&b.is_inf);
+
+    return ieee754_special(&sign, &is_nan, &is_inf, &is_zero, &finite);
+}
+
+/*
+*   f32 u32
+*/
+
+// Plaintext runs of the soft float, the exact oracle of FheF32
+pub fn ieee754_add(a: u32, b: u32) -> u32 {
+    return ieee754_add_generic::<u32, u64>(&a, &b);
+}
+
+pub fn ieee754_mul(a: u32, b: u32) -> u32 {
+    return ieee754_mul_generic::<u32, u64>(&a, &b);
+}
+
+pub fn ieee754_div(a: u32, b: u32) -> u32 {
+    return ieee754_div_generic::<u32, u64>(&a, &b);
+}
+
+/*
+*   FheF32
 */
 
-pub fn fheuint32_to_ieee754(significand: &FheUint32) -> FheUint32 {
-    let mut shifts: FheUint32 = significand.clone() & (1 << 23);
-    let zero_found: FheBool = shifts.eq(0);
+// Encrypted IEEE 754 single precision number, all of the operations round to the nearest, ties to even
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FheF32 {
+    pub bits: FheUint32,
+}
+
+impl FheF32 {
+    pub fn new(bits: FheUint32) -> Self {
+        return FheF32 { bits };
+    }
+
+    pub fn encrypt(value: f32, client_key: &ClientKey) -> Self {
+        return FheF32::new(FheUint32::encrypt(value.to_bits(), client_key));
+    }
+
+    pub fn encrypt_trivial(value: f32) -> Self {
+        return FheF32::new(FheUint32::encrypt_trivial(value.to_bits()));
+    }
+
+    pub fn decrypt(&self, client_key: &ClientKey) -> f32 {
+        let bits: u32 = self.bits.decrypt(client_key);
+        return f32::from_bits(bits);
+    }
+
+    pub fn abs(&self) -> Self {
+        return FheF32::new(self.bits.clone() & 0x7FFFFFFF);
+    }
+
+    pub fn is_nan(&self) -> FheBool {
+        return (self.bits.clone() & 0x7FFFFFFF).gt(IEEE754_INFINITY);
+    }
+
+    pub fn sqrt(&self) -> Self {
+        return FheF32::new(fheuint32_fsqrt(&self.bits));
+    }
+}
+
+impl FheEq<&FheF32> for FheF32 {
+    // Zeroes are equal regardless of their sign, NaNs are not equal to anything
+    fn eq(&self, other: &FheF32) -> FheBool {
+        let are_zeroes: FheBool = ((self.bits.clone() | other.bits.clone()) & 0x7FFFFFFF).eq(0);
+        let is_nan: FheBool = self.is_nan() | other.is_nan();
+
+        return (self.bits.eq(&other.bits) | are_zeroes) & !is_nan;
+    }
+
+    fn ne(&self, other: &FheF32) -> FheBool {
+        return !FheEq::eq(self, other);
+    }
+}
+
+impl FheOrd<&FheF32> for FheF32 {
+    fn lt(&self, other: &FheF32) -> FheBool {
+        let are_zeroes: FheBool = ((self.bits.clone() | other.bits.clone()) & 0x7FFFFFFF).eq(0);
+        let is_nan: FheBool = self.is_nan() | other.is_nan();
+        let self_negative: FheBool = (self.bits.clone() >> 31u32).eq(1);
+        let other_negative: FheBool = (other.bits.clone() >> 31u32).eq(1);
+
+        // Negative magnitudes are ordered in reverse
+        let is_less: FheBool = bool_select(
+            &self_negative,
+            &(!other_negative.clone() | self.bits.gt(&other.bits)),
+            &(!other_negative & self.bits.lt(&other.bits))
+        );
+
+        return is_less & !are_zeroes & !is_nan;
+    }
+
+    fn le(&self, other: &FheF32) -> FheBool {
+        return self.lt(other) | FheEq::eq(self, other);
+    }
+
+    fn gt(&self, other: &FheF32) -> FheBool {
+        return other.lt(self);
+    }
+
+    fn ge(&self, other: &FheF32) -> FheBool {
+        return other.le(self);
+    }
+}
+
+impl FheMin<&FheF32> for FheF32 {
+    type Output = FheF32;
+
+    // A NaN operand is ignored in favour of the other one
+    fn min(&self, other: &FheF32) -> FheF32 {
+        let is_other: FheBool = other.lt(self) | self.is_nan();
+
+        return FheF32::new(is_other.if_then_else(&other.bits, &self.bits));
+    }
+}
+
+impl FheMax<&FheF32> for FheF32 {
+    type Output = FheF32;
+
+    // A NaN operand is ignored in favour of the other one
+    fn max(&self, other: &FheF32) -> FheF32 {
+        let is_other: FheBool = other.gt(self) | self.is_nan();
 
-    shifts = zero_found.if_then_else(&fheuint32_calculate_shifts(&significand, &shifts), &shifts);
+        return FheF32::new(is_other.if_then_else(&other.bits, &self.bits));
+    }
+}
+
+impl Neg for &FheF32 {
+    type Output = FheF32;
+
+    fn neg(self) -> FheF32 {
+        return FheF32::new(self.bits.clone() ^ 0x80000000);
+    }
+}
+
+impl Add<&FheF32> for &FheF32 {
+    type Output = FheF32;
+
+    fn add(self, other: &FheF32) -> FheF32 {
+        return FheF32::new(ieee754_add_generic::<FheUint32, FheUint64>(&self.bits, &other.bits));
+    }
+}
+
+impl Sub<&FheF32> for &FheF32 {
+    type Output = FheF32;
+
+    fn sub(self, other: &FheF32) -> FheF32 {
+        return self + &-other;
+    }
+}
+
+impl Mul<&FheF32> for &FheF32 {
+    type Output = FheF32;
 
-    let exponent: FheUint32 = -shifts.clone() + 150;
-    let ushifts: FheUint32 = shifts.cast_into();
-    return (exponent << 23u32) | ((significand.clone() << ushifts) & 0x7FFFFF);
+    fn mul(self, other: &FheF32) -> FheF32 {
+        return FheF32::new(ieee754_mul_generic::<FheUint32, FheUint64>(&self.bits, &other.bits));
+    }
+}
+
+impl Div<&FheF32> for &FheF32 {
+    type Output = FheF32;
+
+    fn div(self, other: &FheF32) -> FheF32 {
+        return FheF32::new(ieee754_div_generic::<FheUint32, FheUint64>(&self.bits, &other.bits));
+    }
 }
 
-fn fheuint32_calculate_shifts(significand: &FheUint32, shifts: &FheUint32) -> FheUint32 {
-    let mut cloned_shifts: FheUint32 = shifts.clone();
-    let mut zero_found: FheUint32 = !shifts.clone();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::test_client_key;
+
+    // Zero, the edges of the exactly representable integers, the ties around them and the maximum
+    const U64_PATTERNS: [u64; 12] = [
+        0, 1, 2, 3,
+        (1 << 53) - 1, 1 << 53, (1 << 53) + 1, (1 << 53) + 3,
+        (1 << 63) - 1, 1 << 63, u64::MAX - 1, u64::MAX,
+    ];
+
+    // The conversion rounds, whenever the integer does not survive the round trip
+    fn assert_u64_to_ieee754(input: u64) {
+        let expected: f64 = input as f64;
+
+        assert_eq!(u64_to_ieee754(input), (expected.to_bits(), expected as u128 != input as u128), "{}", input);
+    }
+
+    fn assert_u32_to_ieee754(input: u32) {
+        let expected: f32 = input as f32;
+
+        assert_eq!(u32_to_ieee754_2nd(input), (expected.to_bits(), expected as u64 != input as u64), "{}", input);
+    }
+
+    #[test]
+    fn u64_to_ieee754_matches_special_patterns() {
+        U64_PATTERNS.into_iter().for_each(assert_u64_to_ieee754);
+    }
+
+    #[test]
+    fn u64_to_ieee754_matches_random_patterns() {
+        // Shift the random bits to hit every magnitude
+        (0..100_000).for_each(|i| assert_u64_to_ieee754(rand::random::<u64>() >> (i % 64)));
+    }
+
+    #[test]
+    fn u32_to_ieee754_matches_random_patterns() {
+        [0, 1, (1 << 24) - 1, 1 << 24, (1 << 24) + 1, (1 << 24) + 3, u32::MAX].into_iter().for_each(assert_u32_to_ieee754);
+        (0..100_000).for_each(|i| assert_u32_to_ieee754(rand::random::<u32>() >> (i % 32)));
+    }
+
+    // Zeroes, subnormals, the edges of the normals, the ties around one, the overflow and the special values
+    const SOFT_FLOAT_PATTERNS: [f32; 26] = [
+        0.0, -0.0, 1e-45, -1e-45, 1.1754942e-38, f32::MIN_POSITIVE, -f32::MIN_POSITIVE, 1.5e-38,
+        1.0, -1.0, 1.5, -3.0, 0.1, 1.0 + f32::EPSILON, f32::EPSILON / 2.0, 3.0 * f32::EPSILON / 2.0,
+        16777216.0, 16777218.0, 1e30, 1.7e38, f32::MAX, -f32::MAX,
+        f32::INFINITY, f32::NEG_INFINITY, f32::NAN, -f32::NAN,
+    ];
+
+    // NaNs are only compared by their class, the payload of the hardware differs from the default quiet NaN
+    fn assert_soft_float(a: f32, b: f32) {
+        let results: [(&str, u32, f32); 4] = [
+            ("+", ieee754_add(a.to_bits(), b.to_bits()), a + b),
+            ("-", ieee754_add(a.to_bits(), (-b).to_bits()), a - b),
+            ("*", ieee754_mul(a.to_bits(), b.to_bits()), a * b),
+            ("/", ieee754_div(a.to_bits(), b.to_bits()), a / b),
+        ];
+
+        for (operation, actual, expected) in results {
+            if expected.is_nan() {
+                assert!(f32::from_bits(actual).is_nan(), "{:e} {} {:e} = {:e}", a, operation, b, f32::from_bits(actual));
+            } else {
+                assert_eq!(actual, expected.to_bits(), "{:e} {} {:e} = {:e}", a, operation, b, f32::from_bits(actual));
+            }
+        }
+    }
+
+    #[test]
+    fn soft_float_matches_f32_on_special_patterns() {
+        for a in SOFT_FLOAT_PATTERNS {
+            SOFT_FLOAT_PATTERNS.into_iter().for_each(|b| assert_soft_float(a, b));
+        }
+    }
+
+    #[test]
+    fn soft_float_matches_f32_on_random_patterns() {
+        (0..100_000).for_each(|_| assert_soft_float(f32::from_bits(rand::random()), f32::from_bits(rand::random())));
+
+        // Operands of similar magnitudes, which cancel and round in the addition, the full range rarely hits them
+        (0..100_000).for_each(|_| assert_soft_float(rand::random::<f32>() * 4.0 - 2.0, rand::random::<f32>() * 4.0 - 2.0));
 
-    for i in 0..23 {
-        let is_zero: FheBool = (((significand << i as u32) & (1 << 23))).eq(0);
-        zero_found = is_zero.if_then_else(&zero_found, &(!is_zero.clone()).cast_into());
-        cloned_shifts += !zero_found.clone();
+        // Subnormal operands and results
+        let subnormal = || f32::from_bits(rand::random::<u32>() & 0x80FFFFFF);
+        (0..100_000).for_each(|_| assert_soft_float(subnormal(), subnormal()));
     }
 
-    return cloned_shifts;
+    #[test]
+    fn encrypted_fhef32_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+        let (x, y): (f32, f32) = (1.75, -3.0e-3);
+        let a: FheF32 = FheF32::encrypt(x, client_key);
+        let b: FheF32 = FheF32::encrypt(y, client_key);
+
+        assert_eq!((&a + &b).decrypt(client_key).to_bits(), ieee754_add(x.to_bits(), y.to_bits()));
+        assert_eq!((&a * &b).decrypt(client_key).to_bits(), ieee754_mul(x.to_bits(), y.to_bits()));
+        assert_eq!((&a / &b).decrypt(client_key).to_bits(), ieee754_div(x.to_bits(), y.to_bits()));
+        assert!(!a.lt(&b).decrypt(client_key) && a.max(&b).decrypt(client_key) == x);
+    }
+
+    // Zeroes, subnormals, halves around the ties, the edges of i32 and i64, infinities and NaNs
+    const F32_PATTERNS: [f32; 20] = [
+        0.0, -0.0, f32::MIN_POSITIVE, -1e-40, 0.5, -0.5, 1.5, -1.5, 2.5, -2.5,
+        2147483520.0, 2147483648.0, -2147483648.0, -2147483904.0, 9.223372e18, -9.223372e18,
+        f32::MAX, f32::INFINITY, f32::NEG_INFINITY, f32::NAN,
+    ];
+
+    // The rounded conversions are checked against the rounding of the f32 followed by the saturating cast
+    fn assert_ieee754_to_i64(value: f32) {
+        let bits: u32 = value.to_bits();
+
+        assert_eq!(ieee754_to_i64(bits, RoundingMode::Truncate), value as i64, "{}", value);
+        assert_eq!(ieee754_to_i64(bits, RoundingMode::Floor), value.floor() as i64, "floor({})", value);
+        assert_eq!(ieee754_to_i64(bits, RoundingMode::Ceil), value.ceil() as i64, "ceil({})", value);
+        assert_eq!(ieee754_to_i64(bits, RoundingMode::NearestEven), value.round_ties_even() as i64, "round({})", value);
+        assert_eq!(ieee754_to_i32(bits, RoundingMode::NearestEven), value.round_ties_even() as i32, "round({})", value);
+    }
+
+    #[test]
+    fn ieee754_to_i64_matches_special_patterns() {
+        F32_PATTERNS.into_iter().for_each(assert_ieee754_to_i64);
+    }
+
+    #[test]
+    fn ieee754_to_i64_matches_random_patterns() {
+        (0..100_000).for_each(|_| assert_ieee754_to_i64(f32::from_bits(rand::random())));
+
+        // Values with a fractional part, which the full range rarely hits
+        (0..100_000).for_each(|_| assert_ieee754_to_i64(rand::random::<f32>() * 64.0 - 32.0));
+    }
+
+    const ROUNDING_MODES: [RoundingMode; 4] = [
+        RoundingMode::Truncate, RoundingMode::Floor, RoundingMode::Ceil, RoundingMode::NearestEven,
+    ];
+
+    // The rounded values under every mode, then the out of range and the NaN patterns, each under another mode
+    #[test]
+    fn encrypted_ieee754_to_fheint32_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+        let rounded = ROUNDING_MODES.into_iter().flat_map(|mode| [(-2.5f32, mode), (1.5, mode)]);
+        let saturated = [3e9f32, -3e10, f32::INFINITY, f32::NAN].into_iter().zip(ROUNDING_MODES);
+
+        for (value, mode) in rounded.chain(saturated) {
+            let bits: FheUint32 = FheUint32::encrypt(value.to_bits(), client_key);
+            let actual: i32 = fheuint32_ieee754_to_fheint32(&bits, mode).decrypt(client_key);
+
+            assert_eq!(actual, ieee754_to_i32(value.to_bits(), mode), "{:?}({})", mode, value);
+        }
+    }
 }
@@ -0,0 +1,243 @@
+#[cfg(test)]
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tfhe::integer::wopbs::WopbsKey;
+use tfhe::integer::IntegerCiphertext;
+use tfhe::prelude::*;
+use tfhe::shortint::parameters::{
+    ClassicPBSParameters, LweDimension, PARAM_MESSAGE_1_CARRY_1_COMPACT_PK_KS_PBS,
+    PARAM_MESSAGE_2_CARRY_2_COMPACT_PK_KS_PBS, PARAM_MESSAGE_4_CARRY_4_COMPACT_PK_KS_PBS,
+};
+use tfhe::{generate_keys, ClientKey, CompactPublicKey, CompressedServerKey, Config, ConfigBuilder, FheInt32, ServerKey};
+
+/*
+*   Parameter profiles
+*/
+
+// Named TFHE parameter sets, all of them support the encryption with a CompactPublicKey.
+// The profile is recorded with the key, the ciphertexts of a different profile can not be computed on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterProfile {
+    // 2 bits of message and 2 bits of carry per block
+    #[default]
+    Default,
+    // 1 bit of message per block, every bootstrap is cheaper, but an integer takes twice the blocks
+    SmallMessage,
+    // 4 bits of message per block, half the blocks of the default, but every bootstrap is considerably slower
+    HighPrecision,
+    // The default with a tiny LWE dimension, which makes the bootstraps fast. Offers no security, only for tests
+    InsecureTest,
+}
+
+// LWE dimension of the insecure profile
+const INSECURE_LWE_DIMENSION: usize = 128;
+
+impl ParameterProfile {
+    pub const ALL: [ParameterProfile; 4] = [
+        ParameterProfile::Default,
+        ParameterProfile::SmallMessage,
+        ParameterProfile::HighPrecision,
+        ParameterProfile::InsecureTest,
+    ];
+
+    // Name of the profile, the same as in the requests
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParameterProfile::Default => "default",
+            ParameterProfile::SmallMessage => "small_message",
+            ParameterProfile::HighPrecision => "high_precision",
+            ParameterProfile::InsecureTest => "insecure_test",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ParameterProfile> {
+        return ParameterProfile::ALL.into_iter().find(|profile| profile.name() == name);
+    }
+
+    pub fn parameters(&self) -> ClassicPBSParameters {
+        match self {
+            ParameterProfile::Default => PARAM_MESSAGE_2_CARRY_2_COMPACT_PK_KS_PBS,
+            ParameterProfile::SmallMessage => PARAM_MESSAGE_1_CARRY_1_COMPACT_PK_KS_PBS,
+            ParameterProfile::HighPrecision => PARAM_MESSAGE_4_CARRY_4_COMPACT_PK_KS_PBS,
+            ParameterProfile::InsecureTest => ClassicPBSParameters {
+                lwe_dimension: LweDimension(INSECURE_LWE_DIMENSION),
+                ..PARAM_MESSAGE_2_CARRY_2_COMPACT_PK_KS_PBS
+            },
+        }
+    }
+
+    // Whether the keys of the profile can have the function evaluation, tfhe only has it for 2 bits of message and 2 bits of carry
+    pub fn supports_function_evaluation(&self) -> bool {
+        let parameters: ClassicPBSParameters = self.parameters();
+
+        return parameters.message_modulus.0 == 4 && parameters.carry_modulus.0 == 4;
+    }
+
+    // Configuration of the keys, the table lookups need the function evaluation enabled, which makes the server key considerably larger.
+    // None if the profile does not support it
+    pub fn config(&self, function_evaluation: bool) -> Option<Config> {
+        let builder = ConfigBuilder::default().use_custom_parameters(self.parameters(), None);
+
+        if !function_evaluation {
+            return Some(builder.build());
+        }
+
+        return self.supports_function_evaluation().then(|| builder.enable_function_evaluation().build());
+    }
+
+    // Whether the blocks of an encrypted integer have the message and carry space of the profile.
+    // The insecure profile only differs from the default in the keys, which the ciphertexts do not reveal
+    pub fn is_compatible_fheint32(&self, value: &FheInt32) -> bool {
+        let parameters: ClassicPBSParameters = self.parameters();
+        let (radix, _) = value.clone().into_raw_parts();
+
+        return radix.blocks().iter().all(|block| {
+            block.message_modulus == parameters.message_modulus && block.carry_modulus == parameters.carry_modulus
+        });
+    }
+
+    // Whether the server key was generated with the parameters of the profile, unlike the ciphertexts it tells the insecure profile apart
+    pub fn is_compatible_server_key(&self, server_key: &ServerKey) -> bool {
+        let parameters: ClassicPBSParameters = self.parameters();
+        let integer_key: &tfhe::integer::ServerKey = server_key.as_ref();
+        let key: &tfhe::shortint::ServerKey = integer_key.as_ref();
+
+        return key.message_modulus == parameters.message_modulus
+            && key.carry_modulus == parameters.carry_modulus
+            && key.key_switching_key.output_key_lwe_dimension() == parameters.lwe_dimension
+            && key.key_switching_key.decomposition_level_count() == parameters.ks_level
+            && key.bootstrapping_key.input_lwe_dimension() == parameters.lwe_dimension
+            && key.bootstrapping_key.polynomial_size() == parameters.polynomial_size
+            && key.bootstrapping_key.glwe_size() == parameters.glwe_dimension.to_glwe_size()
+            && key.bootstrapping_key.decomposition_level_count() == parameters.pbs_level;
+    }
+}
+
+/*
+*   Client-side keys
+*/
+
+// Generate the keys on the client, only the public key and the server key are meant to leave it
+pub fn generate_client_keys(config: Config) -> (ClientKey, CompactPublicKey, ServerKey) {
+    let (client_key, server_key) = generate_keys(config);
+    let public_key: CompactPublicKey = CompactPublicKey::new(&client_key);
+
+    return (client_key, public_key, server_key);
+}
+
+// Same as generate_client_keys with a compressed server key, which is a fraction of the size to upload
+pub fn generate_compressed_client_keys(config: Config) -> (ClientKey, CompactPublicKey, CompressedServerKey) {
+    let client_key: ClientKey = ClientKey::generate(config);
+    let public_key: CompactPublicKey = CompactPublicKey::new(&client_key);
+    let server_key: CompressedServerKey = CompressedServerKey::new(&client_key);
+
+    return (client_key, public_key, server_key);
+}
+
+// Encrypt a coordinate with the public key, the server computes on it like on any other FheInt32
+pub fn fheint32_encrypt_public(value: i32, public_key: &CompactPublicKey) -> FheInt32 {
+    return FheInt32::try_encrypt(value, public_key).unwrap();
+}
+
+/*
+*   Server-side keys
+*/
+
+// Deserialize a server key and tell, whether it was generated with the function evaluation. A compressed one is decompressed,
+// which takes a while, and never has it. The parts are read on their own, the assembled key would have to be copied to tell
+pub fn deserialize_server_key(bytes: &[u8], compressed: bool) -> bincode::Result<(ServerKey, bool)> {
+    if compressed {
+        let server_key: CompressedServerKey = bincode::deserialize(bytes)?;
+        return Ok((server_key.decompress(), false));
+    }
+
+    // The key is serialized as the integer key followed by the optional key of the function evaluation
+    let (key, wopbs_key): (tfhe::integer::ServerKey, Option<WopbsKey>) = bincode::deserialize(bytes)?;
+    let function_evaluation: bool = wopbs_key.is_some();
+
+    return Ok((ServerKey::from_raw_parts(key, wopbs_key), function_evaluation));
+}
+
+/*
+*   Test keys
+*/
+
+// Insecure keys shared by the tests of the encrypted functions
+#[cfg(test)]
+fn test_keys() -> &'static (ClientKey, ServerKey) {
+    static KEYS: OnceLock<(ClientKey, ServerKey)> = OnceLock::new();
+
+    return KEYS.get_or_init(|| generate_keys(ParameterProfile::InsecureTest.config(false).unwrap()));
+}
+
+// The server key is set on the calling thread
+#[cfg(test)]
+pub(crate) fn test_client_key() -> &'static ClientKey {
+    let (client_key, server_key) = test_keys();
+    tfhe::set_server_key(server_key.clone());
+
+    return client_key;
+}
+
+#[cfg(test)]
+pub(crate) fn test_server_key() -> &'static ServerKey {
+    return &test_keys().1;
+}
+
+// The keys with the function evaluation, its key is large and slow to generate, so only the table lookups use them
+#[cfg(test)]
+pub(crate) fn test_function_evaluation_client_key() -> &'static ClientKey {
+    static KEYS: OnceLock<(ClientKey, ServerKey)> = OnceLock::new();
+
+    let (client_key, server_key) = KEYS.get_or_init(|| generate_keys(ParameterProfile::InsecureTest.config(true).unwrap()));
+    tfhe::set_server_key(server_key.clone());
+
+    return client_key;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn function_evaluation_needs_two_bits_of_message_and_carry() {
+        assert!(ParameterProfile::Default.config(true).is_some());
+        assert!(ParameterProfile::InsecureTest.config(true).is_some());
+        assert!(ParameterProfile::SmallMessage.config(true).is_none());
+        assert!(ParameterProfile::HighPrecision.config(true).is_none());
+        assert!(ParameterProfile::ALL.iter().all(|profile| profile.config(false).is_some()));
+    }
+
+    #[test]
+    fn server_key_is_only_compatible_with_its_profile() {
+        let server_key: &ServerKey = test_server_key();
+
+        assert!(ParameterProfile::InsecureTest.is_compatible_server_key(server_key));
+        assert!(!ParameterProfile::Default.is_compatible_server_key(server_key));
+        assert!(!ParameterProfile::SmallMessage.is_compatible_server_key(server_key));
+        assert!(!ParameterProfile::HighPrecision.is_compatible_server_key(server_key));
+    }
+
+    #[test]
+    fn deserialized_server_key_tells_the_function_evaluation() {
+        let bytes: Vec<u8> = bincode::serialize(test_server_key()).unwrap();
+        let (server_key, function_evaluation) = deserialize_server_key(&bytes, false).unwrap();
+
+        assert!(!function_evaluation);
+        assert!(ParameterProfile::InsecureTest.is_compatible_server_key(&server_key));
+        assert!(deserialize_server_key(&bytes[..bytes.len() / 2], false).is_err());
+    }
+
+    #[test]
+    fn ciphertext_is_compatible_with_the_profiles_of_its_blocks() {
+        let value: FheInt32 = FheInt32::encrypt(-7i32, test_client_key());
+
+        // The insecure profile only differs from the default in the keys
+        assert!(ParameterProfile::InsecureTest.is_compatible_fheint32(&value));
+        assert!(ParameterProfile::Default.is_compatible_fheint32(&value));
+        assert!(!ParameterProfile::SmallMessage.is_compatible_fheint32(&value));
+        assert!(!ParameterProfile::HighPrecision.is_compatible_fheint32(&value));
+    }
+}
@@ -1,4 +1,14 @@
+pub mod bits;
+pub mod cordic;
+pub mod diff;
+pub mod fixed;
+pub mod geo;
+pub mod homo;
 pub mod ieee754;
+pub mod keys;
+pub mod lut;
+pub mod metric;
+pub mod piecewise;
 pub mod sqrt;
 pub mod db;
 pub mod handler;
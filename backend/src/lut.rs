@@ -0,0 +1,67 @@
+use tfhe::{prelude::*, FheUint8};
+
+use crate::sqrt::isqrt_uint;
+
+/*
+*   FheUint8
+*/
+
+// Evaluate a public function on an encrypted byte with one lookup table bootstrap, the output is truncated to a byte.
+// The keys have to be generated with ConfigBuilder::enable_function_evaluation
+pub fn fheuint8_lut<F: Fn(u64) -> u64>(input: &FheUint8, func: F) -> FheUint8 {
+    return input.map(|x| func(x) & 0xFF);
+}
+
+// Evaluate a public table, which has an entry for every byte
+pub fn fheuint8_table(input: &FheUint8, table: &[u8; 256]) -> FheUint8 {
+    return fheuint8_lut(input, |x| table[x as usize] as u64);
+}
+
+// Square root with frac_bits fractional bits in a single lookup, the root of a byte takes 4 bits, which leaves at most 4 fractional bits
+pub fn fheuint8_sqrt_lut(input: &FheUint8, frac_bits: u32) -> FheUint8 {
+    assert!(frac_bits <= 4, "The root of a byte does not fit into a byte with {} fractional bits", frac_bits);
+
+    return fheuint8_lut(input, |x| sqrt_lut(x as u8, frac_bits) as u64);
+}
+
+/*
+*   u8
+*/
+
+// Entry of the square root table, mirrors fheuint8_sqrt_lut
+pub fn sqrt_lut(x: u8, frac_bits: u32) -> u8 {
+    return isqrt_uint(&((x as u32) << (2 * frac_bits))) as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use tfhe::ClientKey;
+
+    use super::*;
+    use crate::keys::test_function_evaluation_client_key;
+
+    #[test]
+    fn sqrt_lut_is_the_floor_of_the_scaled_root() {
+        for frac_bits in 0..=4 {
+            for x in 0..=u8::MAX {
+                let expected: f64 = (((x as u32) << (2 * frac_bits)) as f64).sqrt().floor();
+
+                assert_eq!(sqrt_lut(x, frac_bits) as f64, expected, "sqrt_lut({}, {})", x, frac_bits);
+            }
+        }
+    }
+
+    #[test]
+    fn encrypted_lut_matches_plaintext() {
+        let client_key: &ClientKey = test_function_evaluation_client_key();
+
+        for x in [0u8, 2, 99, 255] {
+            let input: FheUint8 = FheUint8::encrypt(x, client_key);
+            let root: u8 = fheuint8_sqrt_lut(&input, 4).decrypt(client_key);
+            let doubled: u8 = fheuint8_lut(&input, |x| 2 * x).decrypt(client_key);
+
+            assert_eq!(root, sqrt_lut(x, 4), "sqrt_lut({})", x);
+            assert_eq!(doubled, x.wrapping_mul(2), "2 * {}", x);
+        }
+    }
+}
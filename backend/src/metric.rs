@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use tfhe::{prelude::*, FheBool, FheInt32, FheUint32, FheUint64};
+
+use crate::diff::{abs_diff, fheint32_abs_diff, fheint32_squared_diff, squared_diff};
+
+/*
+*   Distance metric
+*/
+
+// Metric of the distance between two points, from the absolute differences along the axes.
+// Only the Euclidean distance takes a square root, the others are exact integers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    Manhattan,
+    Chebyshev,
+    SquaredEuclidean,
+    #[default]
+    Euclidean,
+}
+
+impl DistanceMetric {
+    pub fn needs_sqrt(&self) -> bool {
+        return *self == DistanceMetric::Euclidean;
+    }
+
+    // The differences of the points (x, y) combined into one integer, the Euclidean distance is left squared for the square root.
+    // None if the combination does not fit into an i32
+    pub fn combine(&self, a: (i32, i32), b: (i32, i32)) -> Option<i32> {
+        let (dx, dy) = (abs_diff(a.0, b.0) as u64, abs_diff(a.1, b.1) as u64);
+
+        let combined: u64 = match self {
+            DistanceMetric::Manhattan => dx + dy,
+            DistanceMetric::Chebyshev => std::cmp::max(dx, dy),
+            DistanceMetric::SquaredEuclidean | DistanceMetric::Euclidean => squared_diff(a.0, b.0).checked_add(squared_diff(a.1, b.1))?,
+        };
+
+        return i32::try_from(combined).ok();
+    }
+
+    pub fn distance(&self, a: (i32, i32), b: (i32, i32)) -> Option<f64> {
+        let combined: f64 = self.combine(a, b)? as f64;
+
+        return Some(if self.needs_sqrt() { combined.sqrt() } else { combined });
+    }
+
+    // Mirrors combine on the encrypted points, together with an encrypted overflow flag in place of None.
+    // The differences are combined in 64 bits, the sums are exact and the squares only wrap, when one of them already overflows
+    pub fn fheint32_combine(&self, a: (&FheInt32, &FheInt32), b: (&FheInt32, &FheInt32)) -> (FheInt32, FheBool) {
+        let (combined, overflow): (FheUint64, FheBool) = match self {
+            DistanceMetric::Manhattan => {
+                let dx: FheUint64 = fheint32_abs_diff(a.0, b.0).cast_into();
+                let dy: FheUint64 = fheint32_abs_diff(a.1, b.1).cast_into();
+
+                (dx + dy, FheBool::encrypt_trivial(false))
+            }
+
+            DistanceMetric::Chebyshev => {
+                let dx: FheUint32 = fheint32_abs_diff(a.0, b.0);
+                let dy: FheUint32 = fheint32_abs_diff(a.1, b.1);
+
+                (dx.max(&dy).cast_into(), FheBool::encrypt_trivial(false))
+            }
+
+            DistanceMetric::SquaredEuclidean | DistanceMetric::Euclidean => {
+                let sx: FheUint64 = fheint32_squared_diff(a.0, b.0);
+                let sy: FheUint64 = fheint32_squared_diff(a.1, b.1);
+
+                ((&sx + &sy), (sx | sy).gt(i32::MAX as u64))
+            }
+        };
+
+        let overflow: FheBool = overflow | combined.gt(i32::MAX as u64);
+
+        return (combined.cast_into(), overflow);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tfhe::ClientKey;
+
+    use super::*;
+    use crate::keys::test_client_key;
+
+    const METRICS: [DistanceMetric; 4] = [
+        DistanceMetric::Manhattan,
+        DistanceMetric::Chebyshev,
+        DistanceMetric::SquaredEuclidean,
+        DistanceMetric::Euclidean,
+    ];
+
+    #[test]
+    fn combine_of_known_points() {
+        let (a, b) = ((-3, 4), (0, 0));
+
+        assert_eq!(DistanceMetric::Manhattan.combine(a, b), Some(7));
+        assert_eq!(DistanceMetric::Chebyshev.combine(a, b), Some(4));
+        assert_eq!(DistanceMetric::SquaredEuclidean.combine(a, b), Some(25));
+        assert_eq!(DistanceMetric::Euclidean.distance(a, b), Some(5.0));
+    }
+
+    #[test]
+    fn combine_overflows_past_i32() {
+        let (a, b) = ((i32::MIN, 0), (i32::MAX, 0));
+
+        METRICS.into_iter().for_each(|metric| assert_eq!(metric.combine(a, b), None, "{:?}", metric));
+        METRICS.into_iter().for_each(|metric| assert_eq!(metric.combine(a, a), Some(0), "{:?}", metric));
+
+        // The largest squared distance, that still fits, and the first one, that does not
+        assert_eq!(DistanceMetric::SquaredEuclidean.combine((0, 0), (46340, 0)), Some(46340 * 46340));
+        assert_eq!(DistanceMetric::SquaredEuclidean.combine((0, 0), (46341, 0)), None);
+        assert_eq!(DistanceMetric::Manhattan.combine((0, 0), (i32::MAX, 1)), None);
+    }
+
+    // The encrypted flag is set, whenever the plaintext combination is None
+    #[test]
+    fn encrypted_combine_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+        let encrypt = |(x, y): (i32, i32)| (FheInt32::encrypt(x, client_key), FheInt32::encrypt(y, client_key));
+
+        let cases = METRICS.into_iter().map(|metric| (metric, (-3, 4), (0, 0)))
+            .chain([(DistanceMetric::SquaredEuclidean, (0, 0), (46341, 0)), (DistanceMetric::Manhattan, (i32::MIN, 0), (i32::MAX, 1))]);
+
+        for (metric, a, b) in cases {
+            let ((ax, ay), (bx, by)) = (encrypt(a), encrypt(b));
+            let (combined, overflow) = metric.fheint32_combine((&ax, &ay), (&bx, &by));
+            let combined: i32 = combined.decrypt(client_key);
+            let overflow: bool = overflow.decrypt(client_key);
+
+            match metric.combine(a, b) {
+                Some(expected) => assert!(!overflow && combined == expected, "{:?} = {}", metric, combined),
+                None => assert!(overflow, "{:?} does not overflow", metric),
+            }
+        }
+    }
+}
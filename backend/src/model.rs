@@ -1,5 +1,7 @@
 use sqlx::FromRow;
 use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
 
 // --------------------------------
 // |    Ciphertext Table Model    |
@@ -9,6 +11,8 @@ use serde::{Deserialize, Serialize};
 pub struct CiphertextDistances {
     pub id: Option<Vec<u8>>,
     pub distance: Option<Vec<u8>>,
+    // The result without the distance as JSON, so the cached results keep their format, scale and rounding flag
+    pub metadata: Option<String>,
 }
 
 // -------------------------------
@@ -20,3 +24,18 @@ pub struct PlaintextDistances {
     pub id: Option<Vec<u8>>,
     pub distance: Option<Vec<u8>>,
 }
+
+// --------------------------------
+// |    Server Key Table Model    |
+// --------------------------------
+
+// A registered server key without the key itself, for the listings
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct ServerKeys {
+    pub id: Uuid,
+    pub owner: String,
+    pub profile: String,
+    pub compressed: bool,
+    pub function_evaluation: bool,
+    pub created_at: DateTime<Utc>,
+}
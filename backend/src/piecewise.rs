@@ -0,0 +1,213 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tfhe::{prelude::*, FheBool, FheInt32, FheInt64};
+
+use crate::fixed::FheFixed;
+
+/*
+*   Piecewise-linear function
+*/
+
+// Why the points do not make a piecewise-linear function
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PiecewiseError {
+    TooFewPoints,
+    // The x of the point at the index is not above the one before
+    Unsorted(usize),
+    // The differences of the point at the index and the one before do not fit into an i64
+    Overflow(usize),
+}
+
+impl fmt::Display for PiecewiseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PiecewiseError::TooFewPoints => write!(f, "A piecewise-linear function needs at least two points"),
+            PiecewiseError::Unsorted(i) => write!(f, "The point {} is not right of the one before", i),
+            PiecewiseError::Overflow(i) => write!(f, "The point {} is too far from the one before", i),
+        }
+    }
+}
+
+impl std::error::Error for PiecewiseError {}
+
+// Public piecewise-linear function through the points sorted by x, it is extended linearly past the first and the last point.
+// The deserialized points are checked the same as the constructed ones
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<(i64, i64)>", into = "Vec<(i64, i64)>")]
+pub struct PiecewiseLinear {
+    points: Vec<(i64, i64)>,
+}
+
+impl PiecewiseLinear {
+    // There have to be at least two points with strictly increasing x, the differences of the neighbours have to fit into an i64
+    pub fn new(points: Vec<(i64, i64)>) -> Result<Self, PiecewiseError> {
+        if points.len() < 2 {
+            return Err(PiecewiseError::TooFewPoints);
+        }
+
+        for i in 1..points.len() {
+            let ((x0, y0), (x1, y1)) = (points[i - 1], points[i]);
+
+            if x0 >= x1 {
+                return Err(PiecewiseError::Unsorted(i));
+            }
+
+            if x1.checked_sub(x0).is_none() || y1.checked_sub(y0).is_none() {
+                return Err(PiecewiseError::Overflow(i));
+            }
+        }
+
+        return Ok(PiecewiseLinear { points });
+    }
+
+    // The points in fixed-point with frac_bits fractional bits, rounded to the nearest, for FheFixed with the same FRAC_BITS
+    pub fn from_f64(points: &[(f64, f64)], frac_bits: u32) -> Result<Self, PiecewiseError> {
+        let scale: f64 = (1u64 << frac_bits) as f64;
+
+        return Self::new(points.iter().map(|(x, y)| ((x * scale).round() as i64, (y * scale).round() as i64)).collect());
+    }
+
+    pub fn points(&self) -> &[(i64, i64)] {
+        return &self.points;
+    }
+
+    // Plaintext evaluation, mirrors fheint64_piecewise_linear, the division rounds towards zero and everything else wraps around
+    pub fn evaluate(&self, x: i64) -> i64 {
+        let i: usize = (1..self.points.len() - 1).rev().find(|&i| x >= self.points[i].0).unwrap_or(0);
+        let ((x0, y0), (x1, y1)) = (self.points[i], self.points[i + 1]);
+
+        if (y1 - y0) % (x1 - x0) == 0 {
+            return x.wrapping_sub(x0).wrapping_mul((y1 - y0) / (x1 - x0)).wrapping_add(y0);
+        }
+
+        return x.wrapping_sub(x0).wrapping_mul(y1 - y0).wrapping_div(x1 - x0).wrapping_add(y0);
+    }
+}
+
+impl TryFrom<Vec<(i64, i64)>> for PiecewiseLinear {
+    type Error = PiecewiseError;
+
+    fn try_from(points: Vec<(i64, i64)>) -> Result<Self, PiecewiseError> {
+        return PiecewiseLinear::new(points);
+    }
+}
+
+impl From<PiecewiseLinear> for Vec<(i64, i64)> {
+    fn from(function: PiecewiseLinear) -> Self {
+        return function.points;
+    }
+}
+
+/*
+*   FheInt64
+*/
+
+// Evaluate every segment and select the one of the input by comparing against the inner breakpoints, products past 64 bits wrap around
+pub fn fheint64_piecewise_linear(input: &FheInt64, function: &PiecewiseLinear) -> FheInt64 {
+    let points: &[(i64, i64)] = function.points();
+    let mut output: FheInt64 = fheint64_segment(input, points[0], points[1]);
+
+    for i in 1..points.len() - 1 {
+        let is_right: FheBool = input.ge(points[i].0);
+        output = is_right.if_then_else(&fheint64_segment(input, points[i], points[i + 1]), &output);
+    }
+
+    return output;
+}
+
+// The line through both points, integer slopes need no division
+fn fheint64_segment(input: &FheInt64, (x0, y0): (i64, i64), (x1, y1): (i64, i64)) -> FheInt64 {
+    if (y1 - y0) % (x1 - x0) == 0 {
+        return (input.clone() - x0) * ((y1 - y0) / (x1 - x0)) + y0;
+    }
+
+    return ((input.clone() - x0) * (y1 - y0)) / (x1 - x0) + y0;
+}
+
+/*
+*   FheInt32
+*/
+
+// Evaluated in 64 bits, so that the products of the differences and the slopes do not wrap around
+pub fn fheint32_piecewise_linear(input: &FheInt32, function: &PiecewiseLinear) -> FheInt32 {
+    let input: FheInt64 = input.clone().cast_into();
+
+    return fheint64_piecewise_linear(&input, function).cast_into();
+}
+
+/*
+*   FheFixed
+*/
+
+// The points have to be in the same fixed-point format, see PiecewiseLinear::from_f64
+pub fn fhefixed_piecewise_linear<const INT_BITS: u32, const FRAC_BITS: u32>(input: &FheFixed<INT_BITS, FRAC_BITS>, function: &PiecewiseLinear) -> FheFixed<INT_BITS, FRAC_BITS> {
+    return FheFixed::new(fheint64_piecewise_linear(&input.value, function));
+}
+
+#[cfg(test)]
+mod tests {
+    use tfhe::ClientKey;
+
+    use super::*;
+    use crate::keys::test_client_key;
+
+    #[test]
+    fn evaluate_wraps_around_like_the_encrypted_one() {
+        let function: PiecewiseLinear = PiecewiseLinear::new(vec![(1, 0), (3, 3)]).unwrap();
+
+        // i64::MIN - 1 wraps to i64::MAX, whose product with the slope wraps once more
+        assert_eq!(function.evaluate(i64::MIN), (i64::MAX - 2) / 2);
+        assert_eq!(function.evaluate(5), 6);
+    }
+
+    #[test]
+    fn new_rejects_invalid_points() {
+        assert_eq!(PiecewiseLinear::new(vec![(0, 0)]), Err(PiecewiseError::TooFewPoints));
+        assert_eq!(PiecewiseLinear::new(vec![(0, 0), (2, 1), (2, 3)]), Err(PiecewiseError::Unsorted(2)));
+        assert_eq!(PiecewiseLinear::new(vec![(0, 0), (-1, 1)]), Err(PiecewiseError::Unsorted(1)));
+        assert_eq!(PiecewiseLinear::new(vec![(i64::MIN, 0), (i64::MAX, 0)]), Err(PiecewiseError::Overflow(1)));
+        assert_eq!(PiecewiseLinear::new(vec![(0, 0), (1, i64::MIN), (2, i64::MAX)]), Err(PiecewiseError::Overflow(2)));
+
+        // The deserialized points go through the same checks
+        assert!(serde_json::from_str::<PiecewiseLinear>("[[0, 0], [0, 1]]").is_err());
+        assert_eq!(serde_json::from_str::<PiecewiseLinear>("[[0, 0], [1, 1]]").unwrap().points(), &[(0, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn evaluate_selects_the_segment_of_the_input() {
+        let function: PiecewiseLinear = PiecewiseLinear::new(vec![(-10, 5), (0, 0), (4, 8), (10, 11)]).unwrap();
+
+        // The breakpoints, the first and the last one extended past them
+        assert_eq!(function.evaluate(-20), 10);
+        assert_eq!(function.evaluate(-10), 5);
+        assert_eq!(function.evaluate(0), 0);
+        assert_eq!(function.evaluate(4), 8);
+        assert_eq!(function.evaluate(10), 11);
+        assert_eq!(function.evaluate(14), 13);
+
+        // The fractional slopes round the rise from the left point towards zero, the integer one is exact
+        assert_eq!(function.evaluate(-3), 2);
+        assert_eq!(function.evaluate(-9), 5);
+        assert_eq!(function.evaluate(3), 6);
+        assert_eq!(function.evaluate(7), 9);
+        assert_eq!(function.evaluate(-21), 10);
+    }
+
+    #[test]
+    fn encrypted_piecewise_linear_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
+        let function: PiecewiseLinear = PiecewiseLinear::new(vec![(-10, 5), (0, 0), (4, 8)]).unwrap();
+
+        let value: i32 = fheint32_piecewise_linear(&FheInt32::encrypt(-3i32, client_key), &function).decrypt(client_key);
+        assert_eq!(value as i64, function.evaluate(-3));
+
+        let value: i64 = fheint64_piecewise_linear(&FheInt64::encrypt(6i64, client_key), &function).decrypt(client_key);
+        assert_eq!(value, function.evaluate(6));
+
+        // The points and the input in the fixed-point format of FheFixed<8, 8>
+        let function: PiecewiseLinear = PiecewiseLinear::from_f64(&[(-1.0, 0.5), (0.0, 0.0), (0.5, 1.0)], 8).unwrap();
+        let value: f64 = fhefixed_piecewise_linear(&FheFixed::<8, 8>::encrypt(-0.3, client_key), &function).decrypt(client_key);
+        assert_eq!((value * 256.0) as i64, function.evaluate((-0.3f64 * 256.0).round() as i64));
+    }
+}
@@ -1,4 +1,50 @@
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::keys::ParameterProfile;
+use crate::metric::DistanceMetric;
+
+// ----------------------------
+// |    Key Initialization    |
+// ----------------------------
+
+// The table lookups need the keys with the function evaluation enabled, which are considerably larger
+#[cfg(feature = "dev-insecure-keygen")]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InitKeysQuery {
+    #[serde(default)]
+    pub function_evaluation: bool,
+    #[serde(default)]
+    pub profile: ParameterProfile,
+}
+
+// ---------------------
+// |    Server Keys    |
+// ---------------------
+
+// The serialized server key is the body of the request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterServerKeyQuery {
+    // Whether the server key is a CompressedServerKey, it is decompressed once and kept expanded
+    #[serde(default)]
+    pub compressed: bool,
+    // Parameter profile, the key was generated with
+    #[serde(default)]
+    pub profile: ParameterProfile,
+}
+
+// -----------------------------
+// |    Dev-only Encryption    |
+// -----------------------------
+
+// Only used by the dev-insecure-keygen endpoints, the client key never has to leave the client otherwise
+#[cfg(feature = "dev-insecure-keygen")]
+#[derive(Serialize, Deserialize)]
+pub struct EncryptCoordinatesSchema {
+    pub client_key: Vec<u8>,
+    pub coordinate_a: PlaintextCoordinate,
+    pub coordinate_b: PlaintextCoordinate,
+}
 
 // --------------------------------
 // |    Ciphertext Coordinates    |
@@ -6,10 +52,57 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 pub struct CiphertextCoordinatesSchema {
-    pub client_key: Vec<u8>,
-    pub server_key: Vec<u8>,
+    // Either the ID of a registered server key or the serialized server key itself
+    #[serde(default)]
+    pub server_key: Option<Vec<u8>>,
+    #[serde(default)]
+    pub key_id: Option<Uuid>,
+    // Whether the server key sent along is a CompressedServerKey, it is decompressed on every request, unlike a registered one
+    #[serde(default)]
+    pub compressed: bool,
+    // Parameter profile of the ciphertexts, it has to match the one of a registered key
+    #[serde(default)]
+    pub profile: Option<ParameterProfile>,
     pub coordinate_a: CiphertextCoordinate,
     pub coordinate_b: CiphertextCoordinate,
+    // The format and the precision only apply to the Euclidean distance, the other metrics are exact integers
+    #[serde(default)]
+    pub metric: DistanceMetric,
+    #[serde(default)]
+    pub format: DistanceFormat,
+    #[serde(default)]
+    pub precision: Option<Precision>,
+    // Bit width of the radicand, that the client vouches for, radicands of at most 8 bits are rooted by a table lookup
+    #[serde(default)]
+    pub radicand_bits: Option<u32>,
+}
+
+// Format of the encrypted distance, IEEE 754 single precision or fixed-point with a bounded error
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceFormat {
+    #[default]
+    Ieee754,
+    Fixed,
+}
+
+// Precision of the fixed-point distance, either the number of fractional bits or the largest relative error
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    FracBits(u32),
+    RelativeError(f64),
+}
+
+impl Precision {
+    // The root of an integer radicand of at least 1 truncated to N fractional bits is off by less than 2^-N relative to it
+    pub fn frac_bits(&self) -> Option<u32> {
+        match *self {
+            Precision::FracBits(bits) => Some(bits),
+            Precision::RelativeError(error) if error > 0.0 => Some((-error.log2()).ceil().max(0.0) as u32),
+            Precision::RelativeError(_) => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -26,6 +119,8 @@ pub struct CiphertextCoordinate {
 pub struct PlaintextCoordinatesSchema {
     pub coordinate_a: PlaintextCoordinate,
     pub coordinate_b: PlaintextCoordinate,
+    #[serde(default)]
+    pub metric: DistanceMetric,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,3 +128,45 @@ pub struct PlaintextCoordinate {
     pub x: Vec<u8>,
     pub y: Vec<u8>,
 }
+
+// -----------------------------------------
+// |    Ciphertext Geographic Locations    |
+// -----------------------------------------
+
+// Latitude and longitude are FheInt32 in microdegrees
+#[derive(Serialize, Deserialize)]
+pub struct CiphertextLocationsSchema {
+    #[serde(default)]
+    pub server_key: Option<Vec<u8>>,
+    #[serde(default)]
+    pub key_id: Option<Uuid>,
+    #[serde(default)]
+    pub compressed: bool,
+    #[serde(default)]
+    pub profile: Option<ParameterProfile>,
+    pub location_a: CiphertextLocation,
+    pub location_b: CiphertextLocation,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CiphertextLocation {
+    pub lat: Vec<u8>,
+    pub lon: Vec<u8>,
+}
+
+// ----------------------------------------
+// |    Plaintext Geographic Locations    |
+// ----------------------------------------
+
+// Latitude and longitude are i32 in microdegrees
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlaintextLocationsSchema {
+    pub location_a: PlaintextLocation,
+    pub location_b: PlaintextLocation,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlaintextLocation {
+    pub lat: Vec<u8>,
+    pub lon: Vec<u8>,
+}
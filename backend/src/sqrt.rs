@@ -1,61 +1,58 @@
-use std::ptr::null;
+use tfhe::prelude::CastInto;
+use tfhe::{FheInt64, FheUint128, FheUint32, FheUint64};
 
-use num_traits::{Pow, ToPrimitive};
-use tfhe::core_crypto::commons::traits::CastInto;
-use tfhe::prelude::{FheDecrypt, FheEq, FheOrd, IfThenElse};
-use tfhe::{ClientKey, FheBool, FheInt64, FheUint64};
+use crate::bits::ilog2;
+use crate::homo::{HomoBool, HomoCast, HomoUint};
+use crate::ieee754::{ieee754_format, ieee754_normalize};
 
-use crate::ieee754::ieee754_extract_sign;
-
-const IEEE754_MANTISSA_SIZE: u64 = 23;
+pub const IEEE754_MANTISSA_SIZE: u64 = 23;
 
 /*
 *   FheInt64
 */
 
 pub fn isqrt_homo(x: &FheUint64) -> FheInt64 {
-    let common_one: FheUint64 = (x.clone() | 0x8000000000000000) >> 63u64;
-    let mut a: FheUint64 = common_one.clone() << (2u64 * IEEE754_MANTISSA_SIZE);
-    let mut b: FheUint64 = a.clone();
-    let mut c: FheUint64 = a.clone() >> 2u64;
-    let mut s: FheUint64 = a.clone() + b.clone() + c.clone();
-    let mut can_continue: FheBool = common_one.clone().eq(1u64);
-
-    for k in 1..= IEEE754_MANTISSA_SIZE {
-        let is_eq: FheBool = x.eq(&s);
-        let is_gt: FheBool = x.gt(&s);
-        
-        b = can_continue.if_then_else(
-            &(b.clone() >> 1u64),
-            &b.clone()
-        );
-
-        b = (is_eq.clone() & can_continue.clone()).if_then_else(
-            &((b.clone() + c.clone()) >> (IEEE754_MANTISSA_SIZE - k)),
-            &b.clone()
-        );
-        can_continue = (is_eq.clone() & can_continue.clone() & !is_eq.clone()) | (!is_eq.clone() & can_continue.clone());
-        
-        a = (is_gt.clone() & can_continue.clone()).if_then_else(
-            &s.clone(),
-            &a.clone()
-        );
-        b = (is_gt.clone() & can_continue.clone()).if_then_else(
-            &(b.clone() + c.clone()),
-            &b.clone()
-        );
-
-        c = can_continue.if_then_else(
-            &(c.clone() >> 2u64),
-            &c.clone()
-        );
-        s = can_continue.if_then_else(
-            &(a.clone() + b.clone() + c.clone()),
-            &s.clone()
-        );
-    }
+    let (root, _): (FheUint32, FheUint32) = isqrt_generic(x, IEEE754_MANTISSA_SIZE as u32);
+
+    return root.cast_into();
+}
+
+/*
+*   FheUint32
+*/
+
+// Square root of an encrypted IEEE 754 single precision number, rounded to the nearest
+pub fn fheuint32_fsqrt(input: &FheUint32) -> FheUint32 {
+    return fsqrt_generic::<FheUint32, FheUint64>(input);
+}
+
+// Reciprocal square root with the given number of fractional bits, at most 31
+pub fn fheuint32_rsqrt(x: &FheUint32, frac_bits: u32, iterations: u32) -> FheUint32 {
+    return rsqrt_generic::<FheUint32, FheUint64>(x, frac_bits, iterations);
+}
+
+// The n-th root, rounded down
+pub fn fheuint32_iroot(x: &FheUint32, n: u32) -> FheUint32 {
+    return iroot_generic::<FheUint32, FheUint64>(x, n);
+}
+
+/*
+*   FheUint64
+*/
+
+// Square root of an encrypted IEEE 754 double precision number, rounded to the nearest
+pub fn fheuint64_fsqrt(input: &FheUint64) -> FheUint64 {
+    return fsqrt_generic::<FheUint64, FheUint128>(input);
+}
+
+// Reciprocal square root with the given number of fractional bits, at most 63
+pub fn fheuint64_rsqrt(x: &FheUint64, frac_bits: u32, iterations: u32) -> FheUint64 {
+    return rsqrt_generic::<FheUint64, FheUint128>(x, frac_bits, iterations);
+}
 
-    return b.cast_into();
+// The n-th root, rounded down
+pub fn fheuint64_iroot(x: &FheUint64, n: u32) -> FheUint64 {
+    return iroot_generic::<FheUint64, FheUint128>(x, n);
 }
 
 /*
@@ -71,117 +68,312 @@ pub fn find_m_recursive(x: u32, m:u32) -> u32 {
 }
 
 pub fn isqrt(x: u32, m :u32) -> u32 {
-    let mut a: u32 = 1 << (2 * m);
-    let mut b: u32 = 1 << (2 * m);
-    let mut c: u32 = a >> 2;
-    let mut s: u32 = a + b + c;
+    let (root, _): (u32, u32) = isqrt_generic(&x, m);
 
-    for k in 1..m {
-        b >>= 1;
+    return root;
+}
 
-        if s == x {
-            b += c;
-            return b >> (m - k)
-        }
+// Square root of an IEEE 754 single precision number, mirrors fheuint32_fsqrt
+pub fn fsqrt(input: u32) -> u32 {
+    return fsqrt_generic::<u32, u64>(&input);
+}
 
-        if s < x {
-            a = s;
-            b += c;
-        }
+// Reciprocal square root, mirrors fheuint32_rsqrt
+pub fn rsqrt(x: u32, frac_bits: u32, iterations: u32) -> u32 {
+    return rsqrt_generic::<u32, u64>(&x, frac_bits, iterations);
+}
 
-        c >>= 2;
-        s = a + b + c;
-    }
-    return b
+// The n-th root, mirrors fheuint32_iroot
+pub fn iroot(x: u32, n: u32) -> u32 {
+    return iroot_generic::<u32, u64>(&x, n);
+}
+
+/*
+*   f64 u64
+*/
+
+// Square root of an IEEE 754 double precision number, mirrors fheuint64_fsqrt
+pub fn fsqrt64(input: u64) -> u64 {
+    return fsqrt_generic::<u64, u128>(&input);
 }
 
-pub fn fsqrt(m: u64, mut e: u64, mut f: u64) -> u64{
-    // f = f << 32;
-    println!("mant: [u32] {: ^20} [x] {:#018x} [b] {:064b}", f, f, f);
+/*
+*   Generic
+*/
 
-    let mut big_n: u64;
+// Integer square root of any unsigned integer, the number of digits is half of the width, so narrower types take fewer iterations
+pub fn isqrt_uint<T: HomoUint + HomoCast<T>>(x: &T) -> T {
+    let (root, _): (T, T) = isqrt_generic(x, T::BITS / 2 - 1);
 
-    if e % 2 == 0 {
-        big_n = (f << m) << 1;
-    } else {
-        big_n = (f << (m + 1)) << 1;
-        e = e - 1;
+    return root;
+}
+
+// Integer square root of x < 4^(m + 1) and its remainder, digit by digit in radix 4, restoring the remainder when the trial does not fit.
+// The root and the remainder are kept in R, which only has to hold m + 4 bits, while the radicand needs 2m + 2 bits in T
+pub fn isqrt_generic<T, R>(x: &T, m: u32) -> (R, R)
+where
+    T: HomoUint + HomoCast<R>,
+    R: HomoUint<Bool = T::Bool>,
+{
+    let mut root: R = R::constant(0);
+    let mut remainder: R = R::constant(0);
+
+    for k in (0..= m).rev() {
+        // Bring down the next digit of the radicand, the position is public, so the shift costs nothing
+        let digit: R = x.shr_scalar(2 * k).cast().and_scalar(3);
+        remainder = remainder.shl_scalar(2).or(&digit);
+
+        // The next bit of the root is set, whenever 4 * root + 1 still fits into the remainder
+        let trial: R = root.shl_scalar(2).or_scalar(1);
+        let is_fit: T::Bool = remainder.is_ge(&trial);
+
+        remainder = R::select(&is_fit, &remainder.sub(&trial), &remainder);
+        root = root.shl_scalar(1).or(&R::from_bool(&is_fit));
     }
-    println!("bign: [u64] {: ^20} [x] {:#018x} [b] {:064b}", big_n, big_n, big_n);
-    println!("expo: [u64] {: ^20} [x] {:#018x} [b] {:064b}", e, e, e);
 
-    big_n = 158329674399744;
-    let mut sqrt_big_n = isqrt64(big_n, m);
-    println!("bbbn: [u64] {: ^20} [x] {:#018x} [b] {:064b}", sqrt_big_n, sqrt_big_n, sqrt_big_n);
+    return (root, remainder);
+}
 
-    return sqrt_big_n << ((e - (m * 2)) >> 1);
+// Square root of an IEEE 754 number, the format is chosen by the width of T, W has to be twice as wide
+pub fn fsqrt_generic<T, W>(input: &T) -> T
+where
+    T: HomoUint + HomoCast<W>,
+    W: HomoUint<Bool = T::Bool> + HomoCast<T>,
+{
+    let (mantissa_size, bias) = ieee754_format(T::BITS);
+    let exponent_max: u128 = (1 << (T::BITS - 1 - mantissa_size)) - 1;
+    let quiet_bit: u128 = 1 << (mantissa_size - 1);
+
+    let sign: T = input.shr_scalar(T::BITS - 1);
+    let exponent: T = input.shr_scalar(mantissa_size).and_scalar(exponent_max);
+    let mantissa: T = input.and_scalar((1 << mantissa_size) - 1);
+
+    let is_zero: T::Bool = input.shl_scalar(1).is_eq_scalar(0);
+    let is_subnormal: T::Bool = exponent.is_eq_scalar(0);
+    let is_special: T::Bool = exponent.is_eq_scalar(exponent_max);
+    let is_nan: T::Bool = is_special.and(&mantissa.is_ne_scalar(0));
+    let is_negative: T::Bool = sign.is_eq_scalar(1);
+
+    // Restore the hidden bit, subnormals have none and share the exponent with the smallest normal
+    let subnormal_bit: T = T::from_bool(&is_subnormal);
+    let mut m: T = mantissa.or(&subnormal_bit.not().and_scalar(1).shl_scalar(mantissa_size));
+    let mut t: T = exponent.or(&subnormal_bit).add_scalar(bias);
+
+    // Normalize the subnormal mantissa, so that the hidden bit is always set
+    m = ieee754_normalize(&m, &mut t);
+    let m: W = m.cast();
+
+    // Odd exponent gets an extra shift of the mantissa, the halved exponent is then (t >> 1) - bias
+    let is_odd: T::Bool = t.and_scalar(1).is_eq_scalar(1);
+    let big_n: W = W::select(&is_odd, &m.shl_scalar(mantissa_size + 1), &m.shl_scalar(mantissa_size));
+
+    // The root has the hidden bit set at the mantissa size, it fits into T together with the remainder
+    let (root, remainder): (T, T) = isqrt_generic(&big_n, mantissa_size);
+
+    // Round to the nearest, the tie can not happen, since the radicand is an integer
+    let round: T = T::from_bool(&remainder.is_gt(&root));
+
+    // Re-bias the exponent and merge it with the root, the hidden bit is subtracted from the exponent
+    let finite: T = t.shr_scalar(1).sub_scalar(1).shl_scalar(mantissa_size).add(&root).add(&round);
+
+    // Handle the negative numbers, zeroes, infinities and NaNs
+    let default_nan: T = T::constant((exponent_max << mantissa_size) | quiet_bit);
+    let nan: T = T::select(&is_nan, &input.or_scalar(quiet_bit), &default_nan);
+    let special: T = T::select(&is_nan.or(&is_negative), &nan, input);
+    let mut output: T = T::select(&is_negative, &nan, &finite);
+    output = T::select(&is_zero, input, &output);
+    output = T::select(&is_special, &special, &output);
+
+    return output;
 }
 
-/*
-*   u64
-*/
+// Newton-Raphson for 1 / sqrt(x) in fixed point with frac_bits fractional bits, y = y * (3 - x * y^2) / 2.
+// Zero has no reciprocal square root and saturates to the maximum
+pub fn rsqrt_generic<T, W>(x: &T, frac_bits: u32, iterations: u32) -> T
+where
+    T: HomoUint + HomoCast<W>,
+    W: HomoUint<Bool = T::Bool> + HomoCast<T>,
+{
+    assert!(frac_bits < T::BITS && 2 * frac_bits + 2 <= W::BITS, "{} fractional bits do not fit into the width", frac_bits);
+
+    // Start at 2^-ceil(log2(x) / 2), which is within a factor of sqrt(2) of the root, so that the iteration converges
+    let shift: T = ilog2(x).add_scalar(1).shr_scalar(1);
+    let mut y: W = T::constant(1 << frac_bits).shr(&shift).cast();
+    let x_wide: W = x.cast();
+
+    // The iteration approaches the root from below, hence x * y^2 never exceeds 3 and the difference stays positive
+    for _ in 0..iterations {
+        let t: W = x_wide.mul(&y).mul(&y).shr_scalar(frac_bits);
+        y = y.mul(&W::constant(3 << frac_bits).sub(&t)).shr_scalar(frac_bits + 1);
+    }
 
-fn m64(x: u64, m:u64) -> u64 {
-    if (2u64).pow(2 * m as u32) <= x {
-        m64(x, (m + 1) as u64)
-    } else {
-        m - 1
+    return T::select(&x.is_eq_scalar(0), &T::constant(u128::MAX), &y.cast());
+}
+
+// The n-th root rounded down, bit by bit from the highest possible one, the powers are taken in W to not overflow
+pub fn iroot_generic<T, W>(x: &T, n: u32) -> T
+where
+    T: HomoUint + HomoCast<W>,
+    W: HomoUint<Bool = T::Bool> + HomoCast<T>,
+{
+    assert!(n > 0 && T::BITS + n - 1 <= W::BITS, "The {}-th root does not fit into the width", n);
+
+    let x_wide: W = x.cast();
+    let mut root: W = W::constant(0);
+
+    // The root of x < 2^BITS is below 2^ceil(BITS / n), so the candidates stay below 2^(BITS - 1 + n) when raised to n
+    for k in (0..= (T::BITS - 1) / n).rev() {
+        let candidate: W = root.or_scalar(1 << k);
+        let mut power: W = candidate.clone();
+
+        for _ in 1..n {
+            power = power.mul(&candidate);
+        }
+
+        root = W::select(&power.is_gt(&x_wide), &root, &candidate);
     }
+
+    return root.cast();
 }
 
-fn isqrt64(x: u64, m :u64) -> u64 {
-    let mut a: u64 = 1 << (2 * m);
-    let mut b: u64 = 1 << (2 * m);
-    let mut c: u64 = a >> 2;
-    let mut s: u64 = a + b + c;
+#[cfg(test)]
+mod tests {
+    use tfhe::prelude::*;
+    use tfhe::{ClientKey, FheUint8};
+
+    use super::*;
+    use crate::keys::test_client_key;
+
+    // Zeroes, subnormals, the smallest and largest normals, infinities, quiet and signalling NaNs and negatives
+    const F64_PATTERNS: [u64; 16] = [
+        0x0000000000000000, 0x8000000000000000, 0x0000000000000001, 0x000FFFFFFFFFFFFF,
+        0x0010000000000000, 0x3FF0000000000000, 0x4000000000000000, 0x7FEFFFFFFFFFFFFF,
+        0x7FF0000000000000, 0xFFF0000000000000, 0x7FF8000000000000, 0x7FF0000000000001,
+        0xFFF8000000000000, 0xFFF4000000000001, 0xBFF0000000000000, 0x800FFFFFFFFFFFFF,
+    ];
+
+    const F32_PATTERNS: [u32; 16] = [
+        0x00000000, 0x80000000, 0x00000001, 0x007FFFFF,
+        0x00800000, 0x3F800000, 0x40000000, 0x7F7FFFFF,
+        0x7F800000, 0xFF800000, 0x7FC00000, 0x7F800001,
+        0xFFC00000, 0xFFA00001, 0xBF800000, 0x807FFFFF,
+    ];
+
+    // Rust does not specify the bits of a produced NaN, the root of a NaN keeps its payload and is quieted,
+    // the root of a negative number only has to be some NaN
+    fn assert_fsqrt64(bits: u64) {
+        let input: f64 = f64::from_bits(bits);
+        let expected: f64 = input.sqrt();
+        let actual: u64 = fsqrt64(bits);
+
+        if input.is_nan() {
+            assert_eq!(actual, bits | 1 << 51, "sqrt({:#018x})", bits);
+        } else if expected.is_nan() {
+            assert!(f64::from_bits(actual).is_nan(), "sqrt({:#018x}) = {:#018x}", bits, actual);
+        } else {
+            assert_eq!(actual, expected.to_bits(), "sqrt({:#018x})", bits);
+        }
+    }
 
-    for k in 1..(m + 1) {
-        b >>= 1;
-        if s == x {
-            b += c;
-            return b >> (m - k)
+    fn assert_fsqrt(bits: u32) {
+        let input: f32 = f32::from_bits(bits);
+        let expected: f32 = input.sqrt();
+        let actual: u32 = fsqrt(bits);
+
+        if input.is_nan() {
+            assert_eq!(actual, bits | 1 << 22, "sqrt({:#010x})", bits);
+        } else if expected.is_nan() {
+            assert!(f32::from_bits(actual).is_nan(), "sqrt({:#010x}) = {:#010x}", bits, actual);
+        } else {
+            assert_eq!(actual, expected.to_bits(), "sqrt({:#010x})", bits);
         }
+    }
+
+    #[test]
+    fn fsqrt64_matches_special_patterns() {
+        F64_PATTERNS.into_iter().for_each(assert_fsqrt64);
+    }
+
+    #[test]
+    fn fsqrt64_matches_random_patterns() {
+        (0..100_000).for_each(|_| assert_fsqrt64(rand::random()));
+
+        // Random subnormals and random positive normals, which the full range rarely hits
+        (0..10_000).for_each(|_| assert_fsqrt64(rand::random::<u64>() & 0x000FFFFFFFFFFFFF));
+        (0..10_000).for_each(|_| assert_fsqrt64(rand::random::<u64>() & 0x7FFFFFFFFFFFFFFF));
+    }
+
+    #[test]
+    fn fsqrt_matches_special_patterns() {
+        F32_PATTERNS.into_iter().for_each(assert_fsqrt);
+    }
+
+    #[test]
+    fn fsqrt_matches_random_patterns() {
+        (0..100_000).for_each(|_| assert_fsqrt(rand::random()));
+        (0..10_000).for_each(|_| assert_fsqrt(rand::random::<u32>() & 0x007FFFFF));
+    }
+
+    #[test]
+    fn isqrt_uint_matches_isqrt() {
+        (0..=u8::MAX).for_each(|x| assert_eq!(isqrt_uint(&x), x.isqrt(), "isqrt({})", x));
+        (0..=u16::MAX).for_each(|x| assert_eq!(isqrt_uint(&x), x.isqrt(), "isqrt({})", x));
+
+        [0, 1, 2, 3, 4, u32::MAX - 1, u32::MAX].into_iter()
+            .chain((0..10_000).map(|_| rand::random::<u32>()))
+            .for_each(|x| assert_eq!(isqrt_uint(&x), x.isqrt(), "isqrt({})", x));
 
-        if s < x {
-            a = s;
-            b += c;
+        [0, 1, u64::MAX].into_iter()
+            .chain((0..10_000).map(|_| rand::random::<u64>()))
+            .for_each(|x| assert_eq!(isqrt_uint(&x), x.isqrt(), "isqrt({})", x));
+    }
+
+    #[test]
+    fn rsqrt_is_within_two_units() {
+        // Five iterations converge from the initial guess, what is left are the truncations
+        let frac_bits: u32 = 16;
+
+        for x in (1..1 << 16).chain((0..10_000).map(|_| rand::random::<u32>() >> 12).filter(|&x| x > 0)) {
+            let expected: f64 = (1u64 << frac_bits) as f64 / (x as f64).sqrt();
+            let actual: u32 = rsqrt(x, frac_bits, 5);
+
+            assert!((actual as f64 - expected).abs() <= 2.0, "rsqrt({}) = {}, expected {}", x, actual, expected);
         }
 
-        c >>= 2;
-        s = a + b + c;
+        assert_eq!(rsqrt(0, frac_bits, 5), u32::MAX);
     }
-    return b
-}
 
-/*
-*   f64 u64
-*/
+    #[test]
+    fn iroot_is_the_floor_of_the_root() {
+        for n in 1..=8 {
+            for x in [0, 1, 2, 7, 8, 9, u32::MAX].into_iter().chain((0..2_000).map(|_| rand::random::<u32>())) {
+                let root: u128 = iroot(x, n) as u128;
+
+                assert!(root.pow(n) <= x as u128 && (root + 1).pow(n) > x as u128, "iroot({}, {}) = {}", x, n, root);
+            }
+        }
+    }
 
-pub fn fsqrt64(mut expo: u64, mant: u64, fact: u64) -> u64 {
-    println!("expo: [u64] {: ^20} [x] {:#018x} [b] {:064b}", expo, expo, expo);
-    println!("mant: [u64] {: ^20} [x] {:#018x} [b] {:064b}", mant, mant, mant);
-    println!("fact: [u64] {: ^20} [x] {:#018x} [b] {:064b}", fact, fact, fact);
+    #[test]
+    fn encrypted_isqrt_uint_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
 
-    let mut bign: u64 = 0;
+        for x in [0u8, 1, 2, 15, 16, 99, 255] {
+            let root: FheUint8 = isqrt_uint(&FheUint8::encrypt(x, client_key));
+            let root: u8 = root.decrypt(client_key);
 
-    if expo % 2 == 0 {
-        bign = mant << (2 * fact);
-    } else {
-        bign = mant << (2* fact + 1);
-        expo -= 1;
+            assert_eq!(root, isqrt_uint(&x), "isqrt({})", x);
+        }
     }
 
-    println!("bign: [u64] {: ^20} [x] {:#018x} [b] {:064b}", bign, bign, bign);
-    println!("expo: [u64] {: ^20} [x] {:#018x} [b] {:064b}", expo, expo, expo);
+    #[test]
+    fn encrypted_fsqrt_matches_plaintext() {
+        let client_key: &ClientKey = test_client_key();
 
-    let sqrt: u64 = isqrt64(bign, fact);
-    println!("sqrt: [u64] {: ^20} [x] {:#018x} [b] {:064b}", sqrt, sqrt, sqrt);
+        for bits in [0x40000000u32, 0xBF800000] {
+            let root: u32 = fheuint32_fsqrt(&FheUint32::encrypt(bits, client_key)).decrypt(client_key);
 
-    let nexp: u64 = (expo >> 1) - fact;
-    println!("nexp: [u64] {: ^20} [x] {:#018x} [b] {:064b}", nexp, nexp, nexp);
-    if nexp > 0 {
-        return sqrt << nexp;
-    } else {
-        return sqrt >> nexp;
+            assert_eq!(root, fsqrt(bits), "sqrt({:#010x})", bits);
+        }
     }
 }
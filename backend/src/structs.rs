@@ -1,5 +1,162 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
 use sqlx::{Pool, Postgres};
+use tfhe::ServerKey;
+use uuid::Uuid;
+
+use crate::keys::ParameterProfile;
 
 pub struct AppState {
     pub db: Pool<Postgres>,
+    // Deserialized registered server keys, so a key is only read from the database once
+    pub server_keys: Mutex<KeyCache>,
+    // Upper bound of the registered server keys of all owners together
+    pub server_key_store_size: i64,
+}
+
+#[derive(Clone)]
+pub struct RegisteredKey {
+    // Hash of the bearer token of the owner
+    pub owner: String,
+    pub profile: ParameterProfile,
+    // Whether the key has the function evaluation for the table lookups
+    pub function_evaluation: bool,
+    pub server_key: ServerKey,
+}
+
+// The most recently used server keys, an expanded key takes hundreds of megabytes, so only a few are kept in memory
+pub struct KeyCache {
+    capacity: usize,
+    keys: HashMap<Uuid, RegisteredKey>,
+    // Least recently used first
+    order: VecDeque<Uuid>,
+}
+
+impl KeyCache {
+    pub fn new(capacity: usize) -> Self {
+        return KeyCache { capacity, keys: HashMap::new(), order: VecDeque::new() };
+    }
+
+    // The ServerKey is reference counted, the clone is cheap
+    pub fn get(&mut self, id: &Uuid) -> Option<RegisteredKey> {
+        let key: RegisteredKey = self.keys.get(id)?.clone();
+        self.touch(id);
+
+        return Some(key);
+    }
+
+    // Evicts the least recently used key, when the cache is full
+    pub fn insert(&mut self, id: Uuid, key: RegisteredKey) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.keys.insert(id, key).is_some() {
+            self.touch(&id);
+            return;
+        }
+
+        self.order.push_back(id);
+
+        if self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.keys.remove(&evicted);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        if self.keys.remove(id).is_some() {
+            self.order.retain(|other| other != id);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        return self.keys.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.keys.is_empty();
+    }
+
+    // Move the key to the most recently used end
+    fn touch(&mut self, id: &Uuid) {
+        if let Some(position) = self.order.iter().position(|other| other == id) {
+            self.order.remove(position);
+            self.order.push_back(*id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{deserialize_server_key, generate_compressed_client_keys, test_server_key};
+    use tfhe::{prelude::*, FheUint8};
+
+    fn registered_key(owner: &str) -> RegisteredKey {
+        return RegisteredKey { owner: owner.to_string(), profile: ParameterProfile::InsecureTest, function_evaluation: false, server_key: test_server_key().clone() };
+    }
+
+    fn owner(cache: &mut KeyCache, id: &Uuid) -> Option<String> {
+        return cache.get(id).map(|key| key.owner);
+    }
+
+    #[test]
+    fn key_cache_evicts_the_least_recently_used() {
+        let mut cache: KeyCache = KeyCache::new(2);
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+
+        cache.insert(ids[0], registered_key("a"));
+        cache.insert(ids[1], registered_key("b"));
+
+        // The first key was used last, so the second one makes room for the third
+        assert_eq!(owner(&mut cache, &ids[0]).as_deref(), Some("a"));
+        cache.insert(ids[2], registered_key("c"));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(owner(&mut cache, &ids[1]), None);
+        assert_eq!(owner(&mut cache, &ids[0]).as_deref(), Some("a"));
+        assert_eq!(owner(&mut cache, &ids[2]).as_deref(), Some("c"));
+
+        cache.remove(&ids[0]);
+        assert_eq!(owner(&mut cache, &ids[0]), None);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn key_cache_replaces_and_disables() {
+        let mut cache: KeyCache = KeyCache::new(1);
+        let id: Uuid = Uuid::new_v4();
+
+        cache.insert(id, registered_key("a"));
+        cache.insert(id, registered_key("b"));
+        assert_eq!(owner(&mut cache, &id).as_deref(), Some("b"));
+        assert_eq!(cache.len(), 1);
+
+        let mut disabled: KeyCache = KeyCache::new(0);
+        disabled.insert(id, registered_key("a"));
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn compressed_server_key_is_cached_expanded() {
+        let profile: ParameterProfile = ParameterProfile::InsecureTest;
+        let (client_key, _, compressed) = generate_compressed_client_keys(profile.config(false).unwrap());
+
+        // The way of a registered compressed key, from the body of the request into the cache
+        let (server_key, function_evaluation) = deserialize_server_key(&bincode::serialize(&compressed).unwrap(), true).unwrap();
+        assert!(profile.is_compatible_server_key(&server_key));
+        assert!(!function_evaluation);
+
+        let mut cache: KeyCache = KeyCache::new(1);
+        let id: Uuid = Uuid::new_v4();
+        cache.insert(id, RegisteredKey { owner: "a".to_string(), profile, function_evaluation: false, server_key });
+
+        tfhe::set_server_key(cache.get(&id).unwrap().server_key);
+
+        let sum: FheUint8 = FheUint8::encrypt(100u8, &client_key) + FheUint8::encrypt(55u8, &client_key);
+        assert_eq!(FheDecrypt::<u8>::decrypt(&sum, &client_key), 155);
+    }
 }
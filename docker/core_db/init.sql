@@ -2,10 +2,27 @@ CREATE SCHEMA IF NOT EXISTS postgres;
 
 CREATE TABLE IF NOT EXISTS postgres.CiphertextDistances (
     id BYTEA PRIMARY KEY,
-    distance BYTEA
+    distance BYTEA,
+    metadata TEXT
 );
 
 CREATE TABLE IF NOT EXISTS postgres.PlaintextDistances (
     id BYTEA PRIMARY KEY,
     distance BYTEA
 );
+
+CREATE TABLE IF NOT EXISTS postgres.ServerKeys (
+    id UUID PRIMARY KEY,
+    owner TEXT NOT NULL,
+    profile TEXT NOT NULL DEFAULT 'default',
+    server_key BYTEA NOT NULL,
+    compressed BOOLEAN NOT NULL DEFAULT FALSE,
+    function_evaluation BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- The columns added since the tables were first created, for the existing databases
+ALTER TABLE postgres.CiphertextDistances ADD COLUMN IF NOT EXISTS metadata TEXT;
+ALTER TABLE postgres.ServerKeys ADD COLUMN IF NOT EXISTS profile TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE postgres.ServerKeys ADD COLUMN IF NOT EXISTS compressed BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE postgres.ServerKeys ADD COLUMN IF NOT EXISTS function_evaluation BOOLEAN NOT NULL DEFAULT FALSE;
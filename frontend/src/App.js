@@ -50,7 +50,7 @@ let serverKeySerialized = null;
 // Testing variable
 let lastDistance = null;
 
-// Generate cliet and server keys
+// Generate cliet and server keys, the backend only serves /api/init with the dev-insecure-keygen feature
 async function GenerateKeys() {
   try {
     // Make the GET request using Axios
@@ -64,7 +64,7 @@ async function GenerateKeys() {
   }
 }
 
-// Encrypt the coordinates
+// Encrypt the coordinates, the backend only serves /api/encrypt with the dev-insecure-keygen feature
 async function EncryptCoordinates(mgrsA, mgrsB) {
   // Example values for coordinates
   const x1 = mgrsA.easting;